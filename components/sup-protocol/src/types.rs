@@ -330,7 +330,13 @@ impl FromStr for Topology {
         match topology {
             "leader" => Ok(Topology::Leader),
             "standalone" => Ok(Topology::Standalone),
-            _ => Err(net::err(ErrCode::InvalidPayload, "Invalid topology.")),
+            _ => Err(net::err(
+                ErrCode::InvalidPayload,
+                format!(
+                    "Invalid topology '{}', expected one of: standalone, leader",
+                    topology
+                ),
+            )),
         }
     }
 }
@@ -361,7 +367,10 @@ impl FromStr for UpdateStrategy {
             "rolling" => Ok(UpdateStrategy::Rolling),
             _ => Err(net::err(
                 ErrCode::InvalidPayload,
-                "Invalid update strategy.",
+                format!(
+                    "Invalid update strategy '{}', expected one of: none, at-once, rolling",
+                    strategy
+                ),
             )),
         }
     }
@@ -404,6 +413,15 @@ mod test {
         assert!(Topology::from_str(topology_str).is_err());
     }
 
+    #[test]
+    fn topology_from_str_invalid_lists_valid_topologies() {
+        let err = Topology::from_str("smartest-possible").unwrap_err();
+
+        assert!(err.msg.contains("smartest-possible"));
+        assert!(err.msg.contains("standalone"));
+        assert!(err.msg.contains("leader"));
+    }
+
     #[test]
     fn topology_to_string() {
         assert_eq!("standalone", Topology::Standalone.to_string());
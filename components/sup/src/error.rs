@@ -105,6 +105,7 @@ impl SupError {
 #[derive(Debug)]
 pub enum Error {
     Departed,
+    BadBindMapFile(PathBuf, io::Error),
     BadCompositesPath(PathBuf, io::Error),
     BadDataFile(PathBuf, io::Error),
     BadDataPath(PathBuf, io::Error),
@@ -114,9 +115,17 @@ pub enum Error {
     BadSpecsPath(PathBuf, io::Error),
     BadStartStyle(String),
     BadEnvConfig(String),
+    BindValidation {
+        missing: Vec<String>,
+        invalid: Vec<String>,
+    },
     ButterflyError(butterfly::error::Error),
+    CompositeCycle(package::PackageIdent, Vec<String>),
+    CompositeGroupMismatch(package::PackageIdent, Vec<String>),
+    ConflictingConfigSource(PathBuf, String),
     CtlSecretIo(PathBuf, io::Error),
     DepotClient(depot_client::Error),
+    DuplicateBind(String),
     EnvJoinPathsError(env::JoinPathsError),
     ExecCommandNotFound(String),
     FileNotFound(String),
@@ -126,8 +135,23 @@ pub enum Error {
     HabitatCore(hcore::Error),
     TemplateFileError(handlebars::TemplateFileError),
     TemplateRenderError(handlebars::RenderError),
+    IdentOriginMismatch {
+        spec: package::PackageIdent,
+        package: package::PackageIdent,
+    },
+    IncompatibleTopologyStrategy {
+        topology: protocol::types::Topology,
+        strategy: protocol::types::UpdateStrategy,
+    },
+    InvalidApplicationEnvironment(String),
+    InvalidBindMap(PathBuf, String),
     InvalidBinding(String),
+    #[deprecated(note = "use BindValidation")]
     InvalidBinds(Vec<String>),
+    InvalidChannel(String),
+    InvalidConfigFrom(PathBuf),
+    InvalidEncryptedPassword,
+    InvalidGroup(String),
     InvalidKeyParameter(String),
     InvalidPidFile,
     InvalidTopology(String),
@@ -135,6 +159,16 @@ pub enum Error {
     Io(io::Error),
     IPFailed,
     Launcher(launcher_client::Error),
+    MemberGroupOverride {
+        composite: String,
+        member: String,
+        group: String,
+    },
+    MissingCompositeMember {
+        composite: String,
+        member: package::PackageIdent,
+    },
+    #[deprecated(note = "use BindValidation")]
     MissingRequiredBind(Vec<String>),
     MissingRequiredIdent,
     NameLookup(io::Error),
@@ -155,13 +189,19 @@ pub enum Error {
     ProcessLockIO(PathBuf, io::Error),
     RecvError(mpsc::RecvError),
     RenderContextSerialization(serde_json::Error),
+    SelfBind(String),
     ServiceDeserializationError(serde_json::Error),
     ServiceNotLoaded(package::PackageIdent),
     ServiceSerializationError(serde_json::Error),
     ServiceSpecFileIO(PathBuf, io::Error),
+    ServiceSpecJsonParse(serde_json::Error),
+    ServiceSpecJsonRender(serde_json::Error),
     ServiceSpecParse(toml::de::Error),
     ServiceSpecRender(toml::ser::Error),
     SignalFailed,
+    SpecDirLocked(PathBuf, Pid),
+    SpecDirLockIO(PathBuf, io::Error),
+    SpecGlob(PathBuf, glob::PatternError),
     SpecWatcherDirNotFound(String),
     SpecWatcherGlob(glob::PatternError),
     StrFromUtf8Error(str::Utf8Error),
@@ -170,7 +210,18 @@ pub enum Error {
     TomlMergeError(String),
     TomlParser(toml::de::Error),
     TryRecvError(mpsc::TryRecvError),
+    UndefinedSpecVar(String),
+    UnknownSpecField(String),
     UnpackFailed,
+    UnresolvedGroupTemplate(String),
+    UnsatisfiableCompositeBind {
+        composite: String,
+        member: package::PackageIdent,
+        bind: String,
+        satisfying_service: package::PackageIdent,
+    },
+    UnsupportedOrgBind(Vec<String>),
+    UnsupportedSpecFormat(i64),
     UserNotFound(String),
 }
 
@@ -194,6 +245,11 @@ impl fmt::Display for SupError {
                  If you are in doubt, it is better to consider the services managed by this \
                  Supervisor as unsafe to run."
             ),
+            Error::BadBindMapFile(ref path, ref err) => format!(
+                "Unable to read bind map file, {}, {}",
+                path.display(),
+                err
+            ),
             Error::BadDataFile(ref path, ref err) => format!(
                 "Unable to read or write to data file, {}, {}",
                 path.display(),
@@ -218,7 +274,38 @@ impl fmt::Display for SupError {
             Error::BadEnvConfig(ref varname) => {
                 format!("Unable to find valid TOML or JSON in {} ENVVAR", varname)
             }
+            Error::BindValidation {
+                ref missing,
+                ref invalid,
+            } => {
+                let mut problems = Vec::new();
+                if !missing.is_empty() {
+                    problems.push(format!("missing required bind(s), {}", missing.join(", ")));
+                }
+                if !invalid.is_empty() {
+                    problems.push(format!("invalid bind(s), {}", invalid.join(", ")));
+                }
+                format!("Bind validation failed: {}", problems.join("; "))
+            }
             Error::ButterflyError(ref err) => format!("Butterfly error: {}", err),
+            Error::CompositeCycle(ref composite, ref members) => format!(
+                "Members of composite {} bind to each other in a cycle, so no start order exists \
+                 for {}",
+                composite,
+                members.join(", ")
+            ),
+            Error::CompositeGroupMismatch(ref composite, ref members) => format!(
+                "Members of composite {} must share a group and application environment, but \
+                 {} differ from the first member",
+                composite,
+                members.join(", ")
+            ),
+            Error::ConflictingConfigSource(ref path, ref channel) => format!(
+                "Service config is taken from '{}' but updates come from the '{}' channel, only \
+                 one source should be used",
+                path.display(),
+                channel
+            ),
             Error::CtlSecretIo(ref path, ref err) => format!(
                 "IoError while reading or writing ctl secret, {}, {}",
                 path.display(),
@@ -233,18 +320,58 @@ impl fmt::Display for SupError {
             Error::TemplateFileError(ref err) => format!("{:?}", err),
             Error::TemplateRenderError(ref err) => format!("{}", err),
             Error::DepotClient(ref err) => format!("{}", err),
+            Error::DuplicateBind(ref name) => {
+                format!("Bind '{}' is specified more than once", name)
+            }
             Error::EnvJoinPathsError(ref err) => format!("{}", err),
             Error::FileNotFound(ref e) => format!("File not found at: {}", e),
             Error::FileWatcherFileIsRoot => format!("Watched file is root"),
             Error::GroupNotFound(ref e) => format!("No GID for group '{}' could be found", e),
+            Error::IdentOriginMismatch {
+                ref spec,
+                ref package,
+            } => format!(
+                "Service spec ident '{}' is not from the same origin as its package '{}'",
+                spec, package
+            ),
+            Error::IncompatibleTopologyStrategy {
+                ref topology,
+                ref strategy,
+            } => format!(
+                "The {} topology should not be used with the {} update strategy",
+                topology, strategy
+            ),
+            Error::InvalidApplicationEnvironment(ref reason) => {
+                format!("Invalid application environment {}", reason)
+            }
+            Error::InvalidBindMap(ref path, ref e) => {
+                format!("Invalid bind map file {}, {}", path.display(), e)
+            }
             Error::InvalidBinding(ref binding) => format!(
                 "Invalid binding \"{}\", must be of the form <NAME>:<SERVICE_GROUP> or \
                     <SERVICE_NAME>:<NAME>:<SERVICE_GROUP> where <NAME> is a service name,
                     <SERVICE_GROUP> is a valid service group, and <SERVICE_NAME> is the name of
-                    a service within a composite if the given bind is for a composite service.",
+                    a service within a composite if the given bind is for a composite service.
+                    The ':' before <SERVICE_GROUP> may also be written as '='.",
                 binding
             ),
+            #[allow(deprecated)]
             Error::InvalidBinds(ref e) => format!("Invalid bind(s), {}", e.join(", ")),
+            Error::InvalidChannel(ref channel) => format!(
+                "Invalid channel '{}', it may only contain letters, digits, '-' and '_'",
+                channel
+            ),
+            Error::InvalidConfigFrom(ref path) => format!(
+                "Invalid config-from path '{}', it must be an existing directory",
+                path.display()
+            ),
+            Error::InvalidEncryptedPassword => format!(
+                "Invalid svc_encrypted_password, it must be a base64-encoded encrypted password"
+            ),
+            Error::InvalidGroup(ref group) => format!(
+                "Invalid group '{}', it must not be blank or contain '.', '@' or '#'",
+                group
+            ),
             Error::InvalidKeyParameter(ref e) => {
                 format!("Invalid parameter for key generation: {:?}", e)
             }
@@ -254,6 +381,23 @@ impl fmt::Display for SupError {
             Error::Io(ref err) => format!("{}", err),
             Error::IPFailed => format!("Failed to discover this hosts outbound IP address"),
             Error::Launcher(ref err) => format!("{}", err),
+            Error::MemberGroupOverride {
+                ref composite,
+                ref member,
+                ref group,
+            } => format!(
+                "The {} member of the {} composite can't be put in group '{}', every member must \
+                 share the composite's group",
+                member, composite, group
+            ),
+            Error::MissingCompositeMember {
+                ref composite,
+                ref member,
+            } => format!(
+                "The {} composite includes {}, but no service spec was found for it",
+                composite, member
+            ),
+            #[allow(deprecated)]
             Error::MissingRequiredBind(ref e) => {
                 format!("Missing required bind(s), {}", e.join(", "))
             }
@@ -300,6 +444,11 @@ impl fmt::Display for SupError {
             Error::RenderContextSerialization(ref e) => {
                 format!("Unable to serialize rendering context, {}", e)
             }
+            Error::SelfBind(ref name) => format!(
+                "Bind '{}' targets the service's own service group, a service cannot bind to \
+                 itself",
+                name
+            ),
             Error::ServiceDeserializationError(ref e) => {
                 format!("Can't deserialize service status: {}", e)
             }
@@ -312,6 +461,12 @@ impl fmt::Display for SupError {
                 path.display(),
                 err
             ),
+            Error::ServiceSpecJsonParse(ref err) => {
+                format!("Unable to parse service spec JSON, {}", err)
+            }
+            Error::ServiceSpecJsonRender(ref err) => {
+                format!("Service spec JSON could not be rendered successfully: {}", err)
+            }
             Error::ServiceSpecParse(ref err) => {
                 format!("Unable to parse contents of service spec file, {}", err)
             }
@@ -319,6 +474,21 @@ impl fmt::Display for SupError {
                 format!("Service spec could not be rendered successfully: {}", err)
             }
             Error::SignalFailed => format!("Failed to send a signal to the child process"),
+            Error::SpecDirLocked(ref path, ref pid) => format!(
+                "Spec directory '{}' is locked by another process with the pid {}",
+                path.display(),
+                pid
+            ),
+            Error::SpecDirLockIO(ref path, ref err) => format!(
+                "Unable to write or read spec directory lock at {}, {}",
+                path.display(),
+                err
+            ),
+            Error::SpecGlob(ref path, ref e) => format!(
+                "Unable to list spec files in '{}', the path is not a valid glob pattern: {}",
+                path.display(),
+                e
+            ),
             Error::SpecWatcherDirNotFound(ref path) => format!(
                 "Spec directory '{}' not created or is not a directory",
                 path
@@ -330,7 +500,37 @@ impl fmt::Display for SupError {
             Error::TomlMergeError(ref e) => format!("Failed to merge TOML: {}", e),
             Error::TomlParser(ref err) => format!("Failed to parse TOML: {}", err),
             Error::TryRecvError(ref err) => format!("{}", err),
+            Error::UndefinedSpecVar(ref var) => format!(
+                "Service spec references environment variable '{}', which is not set",
+                var
+            ),
+            Error::UnknownSpecField(ref field) => {
+                format!("Service spec has unknown field '{}'", field)
+            }
             Error::UnpackFailed => format!("Failed to unpack a package"),
+            Error::UnresolvedGroupTemplate(ref group) => format!(
+                "Unable to resolve group '{}', only ${{app}} and ${{env}} placeholders are \
+                 supported and they require an application environment",
+                group
+            ),
+            Error::UnsatisfiableCompositeBind {
+                ref composite,
+                ref member,
+                ref bind,
+                ref satisfying_service,
+            } => format!(
+                "The {} bind of {} in the {} composite is satisfied by {}, which is not a member \
+                 of the composite",
+                bind, member, composite, satisfying_service
+            ),
+            Error::UnsupportedOrgBind(ref binds) => format!(
+                "Composite member binds cannot name an organization, {}",
+                binds.join(", ")
+            ),
+            Error::UnsupportedSpecFormat(version) => format!(
+                "Service spec format version {} is newer than this Supervisor supports",
+                version
+            ),
             Error::UserNotFound(ref e) => format!("No UID for user '{}' could be found", e),
         };
         let progname = PROGRAM_NAME.as_str();
@@ -350,6 +550,7 @@ impl fmt::Display for SupError {
 impl error::Error for SupError {
     fn description(&self) -> &str {
         match self.err {
+            Error::BadBindMapFile(_, _) => "Unable to read a bind map file",
             Error::BadCompositesPath(_, _) => "Unable to create the composites directory",
             Error::Departed => "Supervisor has been manually departed",
             Error::BadDataFile(_, _) => "Unable to read or write to a data file",
@@ -360,7 +561,17 @@ impl error::Error for SupError {
             Error::BadSpecsPath(_, _) => "Unable to create the specs directory",
             Error::BadStartStyle(_) => "Unknown start style in service spec",
             Error::BadEnvConfig(_) => "Unknown syntax in Env Configuration",
+            Error::BindValidation { .. } => {
+                "Service binds are missing required package binds or include unknown binds"
+            }
             Error::ButterflyError(ref err) => err.description(),
+            Error::CompositeCycle(_, _) => "Composite members bind to each other in a cycle",
+            Error::CompositeGroupMismatch(_, _) => {
+                "Composite members do not share a group and application environment"
+            }
+            Error::ConflictingConfigSource(_, _) => {
+                "Service config-from path is combined with a non-default channel"
+            }
             Error::CtlSecretIo(_, _) => "IoError while reading ctl secret",
             Error::ExecCommandNotFound(_) => "Exec command was not found on filesystem or in PATH",
             Error::GroupNotFound(_) => "No matching GID for group found",
@@ -369,13 +580,29 @@ impl error::Error for SupError {
             Error::HabitatCommon(ref err) => err.description(),
             Error::HabitatCore(ref err) => err.description(),
             Error::DepotClient(ref err) => err.description(),
+            Error::DuplicateBind(_) => {
+                "Service spec contains more than one bind with the same name"
+            }
             Error::EnvJoinPathsError(ref err) => err.description(),
             Error::FileNotFound(_) => "File not found",
             Error::FileWatcherFileIsRoot => "Watched file is root",
+            Error::IdentOriginMismatch { .. } => {
+                "Service spec ident origin does not match its package's origin"
+            }
+            Error::IncompatibleTopologyStrategy { .. } => {
+                "Topology and update strategy are known to be incompatible"
+            }
+            Error::InvalidApplicationEnvironment(_) => "Application environment is malformed",
+            Error::InvalidBindMap(_, _) => "Bind map file is malformed",
             Error::InvalidBinding(_) => "Invalid binding parameter",
+            #[allow(deprecated)]
             Error::InvalidBinds(_) => {
                 "Service binds detected that are neither required nor optional package binds"
             }
+            Error::InvalidChannel(_) => "Channel name contains invalid characters",
+            Error::InvalidConfigFrom(_) => "Config-from path is not an existing directory",
+            Error::InvalidEncryptedPassword => "Service encrypted password is malformed",
+            Error::InvalidGroup(_) => "Service group name is blank or contains a separator",
             Error::InvalidKeyParameter(_) => "Key parameter error",
             Error::InvalidPidFile => "Invalid child process PID file",
             Error::InvalidTopology(_) => "Invalid topology",
@@ -383,6 +610,9 @@ impl error::Error for SupError {
             Error::Io(ref err) => err.description(),
             Error::IPFailed => "Failed to discover the outbound IP address",
             Error::Launcher(ref err) => err.description(),
+            Error::MemberGroupOverride { .. } => "Composite member overrides the composite's group",
+            Error::MissingCompositeMember { .. } => "Composite member has no service spec",
+            #[allow(deprecated)]
             Error::MissingRequiredBind(_) => {
                 "A service to start without specifying a service group for all required binds"
             }
@@ -411,13 +641,21 @@ impl error::Error for SupError {
             Error::ProcessLockIO(_, _) => "Unable to read or write to a process lock",
             Error::RecvError(_) => "A channel failed to receive a response",
             Error::RenderContextSerialization(_) => "Unable to serialize rendering context",
+            Error::SelfBind(_) => "Service binds to its own service group",
             Error::ServiceDeserializationError(_) => "Can't deserialize service status",
             Error::ServiceNotLoaded(_) => "Service status called when service not loaded",
             Error::ServiceSerializationError(_) => "Can't serialize service to file",
             Error::ServiceSpecFileIO(_, _) => "Unable to write or read to a service spec file",
+            Error::ServiceSpecJsonParse(_) => "Service spec JSON could not be parsed successfully",
+            Error::ServiceSpecJsonRender(_) => {
+                "Service spec JSON could not be rendered successfully"
+            }
             Error::ServiceSpecParse(_) => "Service spec could not be parsed successfully",
             Error::ServiceSpecRender(_) => "Service spec TOML could not be rendered successfully",
             Error::SignalFailed => "Failed to send a signal to the child process",
+            Error::SpecDirLocked(_, _) => "Spec directory is locked by another process",
+            Error::SpecDirLockIO(_, _) => "Unable to read or write to a spec directory lock",
+            Error::SpecGlob(_, _) => "Spec directory path is not a valid glob pattern",
             Error::SpecWatcherDirNotFound(_) => "Spec directory not created or is not a directory",
             Error::SpecWatcherGlob(_) => "Spec watcher file globbing error",
             Error::StrFromUtf8Error(_) => "Failed to convert a str from a &[u8] as UTF-8",
@@ -426,7 +664,15 @@ impl error::Error for SupError {
             Error::TomlMergeError(_) => "Failed to merge TOML!",
             Error::TomlParser(_) => "Failed to parse TOML!",
             Error::TryRecvError(_) => "A channel failed to receive a response",
+            Error::UndefinedSpecVar(_) => "Service spec references an undefined variable",
+            Error::UnknownSpecField(_) => "Service spec has an unknown field",
             Error::UnpackFailed => "Failed to unpack a package",
+            Error::UnresolvedGroupTemplate(_) => "Service group placeholders could not be resolved",
+            Error::UnsatisfiableCompositeBind { .. } => {
+                "Composite member bind is not satisfied by another member"
+            }
+            Error::UnsupportedOrgBind(_) => "Composite member binds name an organization",
+            Error::UnsupportedSpecFormat(_) => "Service spec format version is not supported",
             Error::UserNotFound(_) => "No matching UID for user found",
         }
     }
@@ -71,7 +71,8 @@ pub use self::service::{CompositeSpec, Service, ServiceBind, ServiceSpec, Spec,
                         UpdateStrategy};
 use self::service::{DesiredState, IntoServiceSpec, Pkg, ProcessState};
 use self::service_updater::ServiceUpdater;
-use self::spec_watcher::{SpecWatcher, SpecWatcherEvent};
+pub use self::spec_watcher::{SpecChange, SpecDirLock, SpecPoller, SpecWatcher};
+use self::spec_watcher::SpecWatcherEvent;
 pub use self::sys::Sys;
 use self::user_config_watcher::UserConfigWatcher;
 use census::CensusRing;
@@ -79,7 +80,7 @@ use config::GossipListenAddr;
 use ctl_gateway::{self, CtlRequest};
 use error::{Error, Result, SupError};
 use http_gateway;
-use manager::service::spec::DesiredState as SpecDesiredState;
+use manager::service::spec::{DesiredState as SpecDesiredState, LOADED_FROM_CLI};
 use util;
 use ShutdownReason;
 use VERSION;
@@ -261,6 +262,7 @@ impl Manager {
                 package.ident().name.clone(),
                 package.pkg_services()?,
                 package.bind_map()?,
+                None,
             ),
         };
         Ok(specs)
@@ -326,8 +328,8 @@ impl Manager {
         // unload services, though. Right now we watch files on disk and communicate with the
         // Supervisor asynchronously. We need to move to communicating directly with the
         // Supervisor's main loop through IPC.
-        match SpecWatcher::spec_files(&fs_cfg.specs_path) {
-            Ok(specs) => for spec_file in specs {
+        match SpecWatcher::spec_files_locked(&fs_cfg.specs_path) {
+            Ok((_lock, specs)) => for spec_file in specs {
                 match ServiceSpec::from_file(&spec_file) {
                     Ok(spec) => {
                         if let Err(err) = spec.to_file(&spec_file) {
@@ -456,7 +458,7 @@ impl Manager {
     }
 
     pub fn spec_path_for(cfg: &ManagerConfig, spec: &ServiceSpec) -> PathBuf {
-        Self::specs_path(cfg.sup_root()).join(spec.file_name())
+        spec.path_in(&Self::specs_path(cfg.sup_root()))
     }
 
     pub fn composite_path_for(cfg: &ManagerConfig, spec: &CompositeSpec) -> PathBuf {
@@ -956,6 +958,7 @@ impl Manager {
                 let mut specs = Self::generate_new_specs_from_package(&installed, &opts)?;
 
                 for spec in specs.iter_mut() {
+                    spec.record_load(LOADED_FROM_CLI);
                     Self::save_spec_for(&mgr.cfg, spec)?;
                     req.info(format!(
                         "The {} service was successfully loaded",
@@ -992,7 +995,12 @@ impl Manager {
 
                 match spec {
                     Spec::Service(mut service_spec) => {
+                        let existing_spec = service_spec.clone();
                         opts.into_spec(&mut service_spec);
+                        // Loading an identical spec again isn't a new load.
+                        if service_spec != existing_spec {
+                            service_spec.record_load(LOADED_FROM_CLI);
+                        }
 
                         // Only install if we don't have something
                         // locally; otherwise you could potentially
@@ -1022,14 +1030,38 @@ impl Manager {
                                     None => unreachable!(),
                                 };
 
+                            let member_names: HashSet<String> = existing_service_specs
+                                .iter()
+                                .map(|s| s.ident.name.clone())
+                                .collect();
+                            let mut unknown_binds = vec![];
                             for mut service_spec in existing_service_specs.iter_mut() {
-                                opts.update_composite(&mut bind_map, &mut service_spec);
+                                let existing_spec = service_spec.clone();
+                                unknown_binds = opts.update_composite(
+                                    &mut bind_map,
+                                    &member_names,
+                                    &mut service_spec,
+                                );
+                                if *service_spec != existing_spec {
+                                    service_spec.record_load(LOADED_FROM_CLI);
+                                }
                                 Self::save_spec_for(&mgr.cfg, service_spec)?;
                                 req.info(format!(
                                     "The {} service was successfully loaded",
                                     service_spec.ident
                                 ))?;
                             }
+                            if !unknown_binds.is_empty() {
+                                req.warn(format!(
+                                    "Ignoring bind(s) for services not in the {} composite: {}",
+                                    composite_spec.ident(),
+                                    unknown_binds
+                                        .iter()
+                                        .map(ToString::to_string)
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ))?;
+                            }
                             req.info(format!(
                                 "The {} composite was successfully loaded",
                                 composite_spec.ident()
@@ -1060,8 +1092,11 @@ impl Manager {
 
                             // Generate new specs from the new composite package and
                             // CLI inputs
-                            let new_service_specs =
+                            let mut new_service_specs =
                                 Self::generate_new_specs_from_package(&installed_package, &opts)?;
+                            for spec in new_service_specs.iter_mut() {
+                                spec.record_load(LOADED_FROM_CLI);
+                            }
 
                             // Delete any specs that are not in the new
                             // composite
@@ -1164,7 +1199,7 @@ impl Manager {
         let updated_specs = match Self::existing_specs_for_ident(&mgr.cfg, &ident)? {
             Some(Spec::Service(mut spec)) => {
                 let mut updated_specs = vec![];
-                if spec.desired_state == DesiredState::Down {
+                if spec.desired_state != DesiredState::Up {
                     spec.desired_state = DesiredState::Up;
                     updated_specs.push(spec);
                 }
@@ -1173,7 +1208,7 @@ impl Manager {
             Some(Spec::Composite(_, service_specs)) => {
                 let mut updated_specs = vec![];
                 for mut spec in service_specs {
-                    if spec.desired_state == DesiredState::Down {
+                    if spec.desired_state != DesiredState::Up {
                         spec.desired_state = DesiredState::Up;
                         updated_specs.push(spec);
                     }
@@ -1910,6 +1945,7 @@ impl From<ProcessStatus> for protocol::types::ProcessStatus {
 
 impl From<service::ServiceBind> for protocol::types::ServiceBind {
     fn from(bind: service::ServiceBind) -> Self {
+        // The protocol has no notion of a fallback service group, so it isn't sent.
         let mut proto = protocol::types::ServiceBind::default();
         proto.name = bind.name;
         proto.service_group = bind.service_group.into();
@@ -1937,6 +1973,8 @@ impl Into<service::ServiceBind> for protocol::types::ServiceBind {
             name: self.name,
             service_group: self.service_group.into(),
             service_name: self.service_name,
+            fallback_service_group: None,
+            port: None,
         }
     }
 }
@@ -1944,7 +1982,8 @@ impl Into<service::ServiceBind> for protocol::types::ServiceBind {
 impl From<SpecDesiredState> for i32 {
     fn from(other: SpecDesiredState) -> Self {
         match other {
-            DesiredState::Down => 0,
+            // The protocol has no paused state; a paused service is reported as down.
+            DesiredState::Down | DesiredState::Paused => 0,
             DesiredState::Up => 1,
         }
     }
@@ -26,7 +26,9 @@ use hcore::error::Error as HCoreError;
 use hcore::package::metadata::PackageType;
 use hcore::package::{Identifiable, PackageIdent, PackageInstall};
 use hcore::util::{deserialize_using_from_str, serialize_using_to_string};
+use serde::{self, Deserialize, Deserializer, Serializer};
 
+use super::spec::{BindMap, ServiceSpec};
 use error::{Error, Result, SupError};
 use rand::{thread_rng, Rng};
 use toml;
@@ -51,6 +53,33 @@ pub struct CompositeSpec {
         serialize_with = "serialize_using_to_string"
     )]
     package_ident: PackageIdent,
+
+    /// The services the composite package runs. Composite specs written before this was
+    /// recorded don't list any.
+    #[serde(
+        deserialize_with = "deserialize_idents",
+        serialize_with = "serialize_idents",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    services: Vec<PackageIdent>,
+}
+
+fn deserialize_idents<'de, D>(d: D) -> result::Result<Vec<PackageIdent>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let idents: Vec<String> = Vec::deserialize(d)?;
+    idents
+        .iter()
+        .map(|ident| PackageIdent::from_str(ident).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+fn serialize_idents<S>(idents: &[PackageIdent], s: S) -> result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.collect_seq(idents.iter().map(ToString::to_string))
 }
 
 // NOTE: Yes, this code is largely copied from ServiceSpec, and should
@@ -74,6 +103,7 @@ impl CompositeSpec {
                     Ok(CompositeSpec {
                         ident: original_ident.clone(),
                         package_ident: package_ident,
+                        services: package_install.pkg_services()?,
                     })
                 } else {
                     // HOW DID THIS EVEN HAPPEN?
@@ -105,6 +135,137 @@ impl CompositeSpec {
         format!("{}.{}", self.ident().name, SPEC_FILE_EXT)
     }
 
+    /// Checks that there's a member spec for every service the composite runs, and that every
+    /// member spec has the same group and application environment as the first, as they did when
+    /// the composite was loaded. Member specs can go missing or drift apart when edited on disk.
+    ///
+    /// # Errors
+    ///
+    /// * If a service of the composite has no member spec
+    /// * If any member's group or application environment differs from the first member's
+    pub fn validate_members(&self, members: &[ServiceSpec]) -> Result<()> {
+        if let Some(missing) = self.services.iter().find(|service| {
+            !members
+                .iter()
+                .any(|m| m.ident.origin == service.origin && m.ident.name == service.name)
+        }) {
+            return Err(sup_error!(Error::MissingCompositeMember {
+                composite: self.ident.name.clone(),
+                member: missing.clone(),
+            }));
+        }
+        let first = match members.first() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+        let divergent: Vec<String> = members
+            .iter()
+            .filter(|m| {
+                m.group != first.group || m.application_environment != first.application_environment
+            })
+            .map(|m| m.ident.to_string())
+            .collect();
+        if divergent.is_empty() {
+            Ok(())
+        } else {
+            Err(sup_error!(Error::CompositeGroupMismatch(
+                self.ident.clone(),
+                divergent
+            )))
+        }
+    }
+
+    /// Checks that every bind the composite defines between its members, as recorded in
+    /// `bind_map`, is satisfied by a service which is in `members`. Binds which aren't in
+    /// `bind_map`, or which were pointed at a different service when the composite was loaded,
+    /// deliberately bind to services outside the composite and aren't checked.
+    ///
+    /// # Errors
+    ///
+    /// * If a bind between members is satisfied by a service which isn't a member
+    pub fn validate_binds(&self, members: &[ServiceSpec], bind_map: &BindMap) -> Result<()> {
+        let is_member = |ident: &PackageIdent| {
+            members
+                .iter()
+                .any(|m| m.ident.origin == ident.origin && m.ident.name == ident.name)
+        };
+        for member in members {
+            let mappings = bind_map
+                .iter()
+                .filter(|&(ident, _)| {
+                    ident.origin == member.ident.origin && ident.name == member.ident.name
+                })
+                .flat_map(|(_, mappings)| mappings);
+            for mapping in mappings {
+                let internal = member.binds.iter().any(|bind| {
+                    bind.name == mapping.bind_name
+                        && bind.service_group.service() == mapping.satisfying_service.name
+                });
+                if internal && !is_member(&mapping.satisfying_service) {
+                    return Err(sup_error!(Error::UnsatisfiableCompositeBind {
+                        composite: self.ident.name.clone(),
+                        member: member.ident.clone(),
+                        bind: mapping.bind_name.clone(),
+                        satisfying_service: mapping.satisfying_service.clone(),
+                    }));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The order in which to start the composite's members so that each starts after the
+    /// members it binds to. A member depends on another when one of its binds targets a service
+    /// group of that member's service. Members with no dependency between them keep the order
+    /// they have in `members`, so the order is stable from one call to the next.
+    ///
+    /// # Errors
+    ///
+    /// * If the members' binds form a cycle, in which case the members which are in or depend on
+    ///   the cycle are reported
+    pub fn start_order(&self, members: &[ServiceSpec]) -> Result<Vec<PackageIdent>> {
+        let dependencies: Vec<Vec<usize>> = members
+            .iter()
+            .enumerate()
+            .map(|(i, member)| {
+                members
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, other)| {
+                        i != j
+                            && member
+                                .dependency_groups()
+                                .iter()
+                                .any(|group| group.service() == other.ident.name)
+                    })
+                    .map(|(j, _)| j)
+                    .collect()
+            })
+            .collect();
+        let mut started = vec![false; members.len()];
+        let mut order = Vec::with_capacity(members.len());
+        while order.len() < members.len() {
+            let next = (0..members.len())
+                .find(|&i| !started[i] && dependencies[i].iter().all(|&dep| started[dep]));
+            match next {
+                Some(i) => {
+                    started[i] = true;
+                    order.push(members[i].ident.clone());
+                }
+                None => {
+                    let stuck = members
+                        .iter()
+                        .zip(started.iter())
+                        .filter(|&(_, started)| !started)
+                        .map(|(member, _)| member.ident.to_string())
+                        .collect();
+                    return Err(sup_error!(Error::CompositeCycle(self.ident.clone(), stuck)));
+                }
+            }
+        }
+        Ok(order)
+    }
+
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = File::open(&path)
             .map_err(|err| sup_error!(Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)))?;
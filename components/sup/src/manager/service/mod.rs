@@ -22,7 +22,7 @@ pub mod spec;
 mod supervisor;
 
 use std;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
@@ -49,7 +49,8 @@ use self::dir::SvcDir;
 pub use self::health::{HealthCheck, SmokeCheck};
 use self::hooks::{Hook, HookTable, HOOK_PERMISSIONS};
 pub use self::package::{Env, Pkg};
-pub use self::spec::{BindMap, DesiredState, IntoServiceSpec, ServiceBind, ServiceSpec, Spec};
+pub use self::spec::{BindMap, DesiredState, IntoServiceSpec, ServiceBind, ServiceSpec, Spec,
+                     ValidateOptions};
 use self::supervisor::Supervisor;
 use super::ShutdownReason;
 use super::Sys;
@@ -125,6 +126,8 @@ pub struct Service {
     /// Controls how the presence or absence of bound service groups
     /// impacts the service's start-up.
     binding_mode: BindingMode,
+    /// Per-bind overrides of `binding_mode`, keyed by bind name.
+    binding_mode_overrides: BTreeMap<String, BindingMode>,
     /// Binds specified by the user that are currently mapped to
     /// service groups that do _not_ satisfy the bind's contract, as
     /// defined in the service's current package.
@@ -162,14 +165,20 @@ impl Service {
         manager_fs_cfg: Arc<manager::FsCfg>,
         organization: Option<&str>,
     ) -> Result<Service> {
-        spec.validate(&package)?;
+        spec.validate_with(
+            &package,
+            ValidateOptions {
+                check_config_from: true,
+                ..Default::default()
+            },
+        )?;
         let all_pkg_binds = (&package).all_binds()?;
         let pkg = Pkg::from_install(package)?;
-        let spec_file = manager_fs_cfg.specs_path.join(spec.file_name());
+        let spec_file = spec.path_in(&manager_fs_cfg.specs_path);
         let service_group = ServiceGroup::new(
             spec.application_environment.as_ref(),
             &pkg.name,
-            spec.group,
+            spec.resolved_group()?,
             organization,
         )?;
         let config_root = Self::config_root(&pkg, spec.config_from.as_ref());
@@ -201,6 +210,7 @@ impl Service {
             all_pkg_binds: all_pkg_binds,
             unsatisfied_binds: HashSet::new(),
             binding_mode: spec.binding_mode,
+            binding_mode_overrides: spec.binding_mode_overrides,
             spec_ident: spec.ident,
             spec_file: spec_file,
             topology: spec.topology,
@@ -306,16 +316,11 @@ impl Service {
     pub fn tick(&mut self, census_ring: &CensusRing, launcher: &LauncherCli) -> bool {
         // We may need to block the service from starting until all
         // its binds are satisfied
-        if !self.initialized {
-            match self.binding_mode {
-                BindingMode::Relaxed => (),
-                BindingMode::Strict => {
-                    self.validate_binds(census_ring);
-                    if !self.unsatisfied_binds.is_empty() {
-                        outputln!(preamble self.service_group, "Waiting for service binds...");
-                        return false;
-                    }
-                }
+        if !self.initialized && self.binds.iter().any(|b| self.is_strict_bind(b)) {
+            self.validate_binds(census_ring);
+            if self.unsatisfied_binds.iter().any(|b| self.is_strict_bind(b)) {
+                outputln!(preamble self.service_group, "Waiting for service binds...");
+                return false;
             }
         }
 
@@ -396,6 +401,7 @@ impl Service {
         spec.topology = self.topology;
         spec.update_strategy = self.update_strategy;
         spec.binds = self.binds.clone();
+        spec.binding_mode_overrides = self.binding_mode_overrides.clone();
         spec.config_from = self.config_from.clone();
         if let Some(ref password) = self.svc_encrypted_password {
             spec.svc_encrypted_password = Some(password.clone())
@@ -403,6 +409,16 @@ impl Service {
         spec
     }
 
+    /// Whether the given bind must be satisfied before the service
+    /// may start, taking any per-bind override into account.
+    fn is_strict_bind(&self, bind: &ServiceBind) -> bool {
+        let mode = self.binding_mode_overrides
+            .get(&bind.name)
+            .cloned()
+            .unwrap_or(self.binding_mode);
+        mode == BindingMode::Strict
+    }
+
     /// Iterate through all the service binds, marking any that are
     /// unsatisfied in `self.unsatisfied_binds`.
     ///
@@ -12,45 +12,75 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{HashMap, HashSet};
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
 use std::fmt;
 use std::fs::{self, File};
-use std::io::{BufReader, Read, Write};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, Read, Write};
 use std::iter::FromIterator;
 use std::path::{Path, PathBuf};
 use std::result;
 use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 
+use glob;
 use hcore::channel::STABLE_CHANNEL;
+use hcore::crypto::hash;
 use hcore::package::metadata::BindMapping;
-use hcore::package::{PackageIdent, PackageInstall};
+use hcore::package::{Identifiable, PackageIdent, PackageInstall};
 use hcore::service::{ApplicationEnvironment, ServiceGroup};
 use hcore::url::DEFAULT_BLDR_URL;
 use hcore::util::{deserialize_using_from_str, serialize_using_to_string};
 use protocol;
 use rand::{thread_rng, Rng};
-use serde::{self, Deserialize};
+use serde::{self, Deserialize, Serialize};
+use serde_json;
+use time;
 use toml;
 
 use super::composite_spec::CompositeSpec;
 use super::{BindingMode, Topology, UpdateStrategy};
 use error::{Error, Result, SupError};
+use manager::spec_watcher::{SpecDirLock, SpecWatcher};
+use VERSION;
 
 static LOGKEY: &'static str = "SS";
 static DEFAULT_GROUP: &'static str = "default";
 const SPEC_FILE_EXT: &'static str = "spec";
+/// Recorded as `loaded_from` for specs written by `hab svc load`.
+pub const LOADED_FROM_CLI: &'static str = "cli";
+/// Recorded as `loaded_from` for specs rewritten by `migrate_spec_dir`.
+pub const LOADED_FROM_MIGRATION: &'static str = "migration";
+/// The newest `format_version` of spec file which this Supervisor can read. The version is only
+/// raised for changes which older Supervisors can't read safely; new fields alone don't need it.
+pub const SPEC_FORMAT_VERSION: i64 = 1;
+/// The longest bind string we'll attempt to parse, guarding against oversized input from
+/// untrusted spec files.
+const MAX_BIND_LEN: usize = 4096;
+/// How many more times `write_atomically` tries a rename which was refused with `AlreadyExists`.
+const RENAME_RETRIES: u32 = 3;
 
 pub type BindMap = HashMap<PackageIdent, Vec<BindMapping>>;
 
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum DesiredState {
     Down,
     Up,
+    // Intentionally stopped. Unlike `Down`, a paused service is never started by the Supervisor
+    // on its own, even though its spec is present.
+    Paused,
 }
 
+/// The desired state of a spec which doesn't set one. Services are started unless a spec says
+/// otherwise, so a spec written by hand needn't mention `desired_state` at all.
+pub const DEFAULT_DESIRED_STATE: DesiredState = DesiredState::Up;
+
 impl Default for DesiredState {
     fn default() -> DesiredState {
-        DesiredState::Up
+        DEFAULT_DESIRED_STATE
     }
 }
 
@@ -59,6 +89,7 @@ impl fmt::Display for DesiredState {
         let value = match *self {
             DesiredState::Down => "down",
             DesiredState::Up => "up",
+            DesiredState::Paused => "paused",
         };
         write!(f, "{}", value)
     }
@@ -71,11 +102,90 @@ impl FromStr for DesiredState {
         match value.to_lowercase().as_ref() {
             "down" => Ok(DesiredState::Down),
             "up" => Ok(DesiredState::Up),
+            "paused" => Ok(DesiredState::Paused),
             _ => Err(sup_error!(Error::BadDesiredState(value.to_string()))),
         }
     }
 }
 
+/// Names a single field of a `ServiceSpec`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SpecField {
+    Ident,
+    Group,
+    ApplicationEnvironment,
+    BldrUrl,
+    Channel,
+    Topology,
+    UpdateStrategy,
+    Binds,
+    BindingMode,
+    ConfigFrom,
+    DesiredState,
+    SvcEncryptedPassword,
+    Composite,
+    BindingModeOverrides,
+}
+
+/// A single field that differs between two `ServiceSpec`s, carrying the old and new values
+/// rendered as strings, in that order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SpecFieldChange {
+    Ident(String, String),
+    Group(String, String),
+    ApplicationEnvironment(String, String),
+    BldrUrl(String, String),
+    Channel(String, String),
+    Topology(String, String),
+    UpdateStrategy(String, String),
+    Binds(String, String),
+    BindingMode(String, String),
+    ConfigFrom(String, String),
+    DesiredState(String, String),
+    SvcEncryptedPassword(String, String),
+    Composite(String, String),
+    BindingModeOverrides(String, String),
+}
+
+impl SpecFieldChange {
+    /// Whether a running service would need to be restarted to pick up this change. Flipping
+    /// the desired state is handled by starting or stopping the service, not restarting it.
+    pub fn is_restart_relevant(&self) -> bool {
+        match *self {
+            SpecFieldChange::DesiredState(_, _) => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for SpecFieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (field, old, new) = match *self {
+            SpecFieldChange::Ident(ref o, ref n) => ("ident", o, n),
+            SpecFieldChange::Group(ref o, ref n) => ("group", o, n),
+            SpecFieldChange::ApplicationEnvironment(ref o, ref n) => {
+                ("application_environment", o, n)
+            }
+            SpecFieldChange::BldrUrl(ref o, ref n) => ("bldr_url", o, n),
+            SpecFieldChange::Channel(ref o, ref n) => ("channel", o, n),
+            SpecFieldChange::Topology(ref o, ref n) => ("topology", o, n),
+            SpecFieldChange::UpdateStrategy(ref o, ref n) => ("update_strategy", o, n),
+            SpecFieldChange::Binds(ref o, ref n) => ("binds", o, n),
+            SpecFieldChange::BindingMode(ref o, ref n) => ("binding_mode", o, n),
+            SpecFieldChange::ConfigFrom(ref o, ref n) => ("config_from", o, n),
+            SpecFieldChange::DesiredState(ref o, ref n) => ("desired_state", o, n),
+            SpecFieldChange::SvcEncryptedPassword(ref o, ref n) => {
+                ("svc_encrypted_password", o, n)
+            }
+            SpecFieldChange::Composite(ref o, ref n) => ("composite", o, n),
+            SpecFieldChange::BindingModeOverrides(ref o, ref n) => {
+                ("binding_mode_overrides", o, n)
+            }
+        };
+        write!(f, "{} changed from '{}' to '{}'", field, old, new)
+    }
+}
+
 pub enum Spec {
     Service(ServiceSpec),
     Composite(CompositeSpec, Vec<ServiceSpec>),
@@ -88,6 +198,50 @@ impl Spec {
             &Spec::Service(ref s) => s.ident.as_ref(),
         }
     }
+
+    /// The idents of the services this spec runs: each member of a composite, or the single
+    /// service of a standalone spec.
+    pub fn member_idents(&self) -> Vec<&PackageIdent> {
+        match self {
+            &Spec::Composite(_, ref members) => members.iter().map(|s| &s.ident).collect(),
+            &Spec::Service(ref s) => vec![&s.ident],
+        }
+    }
+
+    /// Loads every service spec in `specs_path`, grouping specs which name a composite together
+    /// with that composite's spec from `composites_path`. Specs which aren't part of a composite
+    /// are returned as `Spec::Service`.
+    ///
+    /// If a composite's spec file is missing, its members are logged and returned as standalone
+    /// `Spec::Service`s instead.
+    ///
+    /// # Errors
+    ///
+    /// * If `specs_path` can't be read
+    /// * If any service spec or composite spec file can't be read or parsed
+    /// * If a composite's members don't share a group and application environment
+    pub fn load_all(specs_path: &Path, composites_path: &Path) -> Result<Vec<Spec>> {
+        let (standalone, members) = load_grouped(specs_path)?;
+        let mut specs: Vec<Spec> = standalone.into_iter().map(Spec::Service).collect();
+        // Visit composites in name order so the result doesn't depend on hashing.
+        let members: BTreeMap<String, Vec<ServiceSpec>> = members.into_iter().collect();
+        for (name, services) in members {
+            let composite_file = composites_path.join(format!("{}.{}", name, SPEC_FILE_EXT));
+            if composite_file.is_file() {
+                let composite = CompositeSpec::from_file(&composite_file)?;
+                composite.validate_members(&services)?;
+                specs.push(Spec::Composite(composite, services));
+            } else {
+                outputln!(
+                    "Composite spec for {} not found at {}, loading its services standalone",
+                    name,
+                    composite_file.display()
+                );
+                specs.extend(services.into_iter().map(Spec::Service));
+            }
+        }
+        Ok(specs)
+    }
 }
 
 pub fn deserialize_application_environment<'de, D>(
@@ -98,26 +252,144 @@ where
 {
     let s: Option<String> = Option::deserialize(d)?;
     if let Some(s) = s {
-        Ok(Some(FromStr::from_str(&s).map_err(serde::de::Error::custom)?))
+        let app_env = check_application_environment(&s).map_err(|reason| {
+            serde::de::Error::custom(format!("Invalid application environment {}", reason))
+        })?;
+        Ok(Some(app_env))
     } else {
         Ok(None)
     }
 }
 
+/// Parses an application environment of the form `<APPLICATION>.<ENVIRONMENT>`, describing what
+/// is wrong with a malformed one.
+///
+/// # Errors
+///
+/// * If `s` has no environment part, or an empty application or environment
+/// * If `s` is otherwise not a valid application environment
+pub fn parse_application_environment(s: &str) -> Result<ApplicationEnvironment> {
+    check_application_environment(s)
+        .map_err(|reason| sup_error!(Error::InvalidApplicationEnvironment(reason)))
+}
+
+fn check_application_environment(s: &str) -> result::Result<ApplicationEnvironment, String> {
+    let mut parts = s.splitn(2, '.');
+    let application = parts.next().unwrap_or("");
+    match parts.next() {
+        None => Err(format!(
+            "'{}', it is missing the environment and must be of the form \
+             <APPLICATION>.<ENVIRONMENT>",
+            s
+        )),
+        Some(_) if application.is_empty() => Err(format!("'{}', the application is empty", s)),
+        Some(environment) if environment.is_empty() => {
+            Err(format!("'{}', the environment is empty", s))
+        }
+        Some(_) => ApplicationEnvironment::from_str(s).map_err(|e| format!("'{}', {}", s, e)),
+    }
+}
+
+/// Deserializes a `Topology` through its `FromStr` implementation so that an unknown value
+/// reports the valid topologies rather than a generic enum variant error. Matching ignores case,
+/// so `Leader` is read as `leader`.
+pub fn deserialize_topology<'de, D>(d: D) -> result::Result<Topology, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(d)?;
+    Topology::from_str(&s.to_lowercase()).map_err(|e| serde::de::Error::custom(e.msg))
+}
+
+/// Deserializes an `UpdateStrategy` the same way as `deserialize_topology`, ignoring case and
+/// reporting the valid update strategies for an unknown value.
+pub fn deserialize_update_strategy<'de, D>(d: D) -> result::Result<UpdateStrategy, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(d)?;
+    UpdateStrategy::from_str(&s.to_lowercase()).map_err(|e| serde::de::Error::custom(e.msg))
+}
+
+/// Deserializes binds from either an array of bind strings, such as
+/// `binds = ["name:service.group"]`, or a table mapping each bind name to its service group,
+/// such as `[binds]` followed by `name = "service.group"`.
+pub fn deserialize_binds<'de, D>(d: D) -> result::Result<Vec<ServiceBind>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct FromListOrTable;
+
+    impl<'de> serde::de::Visitor<'de> for FromListOrTable {
+        type Value = Vec<ServiceBind>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an array of binds or a table of bind names to service groups")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> result::Result<Vec<ServiceBind>, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut binds = Vec::new();
+            while let Some(bind) = seq.next_element()? {
+                binds.push(bind);
+            }
+            Ok(binds)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> result::Result<Vec<ServiceBind>, A::Error>
+        where
+            A: serde::de::MapAccess<'de>,
+        {
+            let mut binds = Vec::new();
+            while let Some((name, group)) = map.next_entry::<String, String>()? {
+                let bind = ServiceBind::from_str(&format!("{}:{}", name, group))
+                    .map_err(serde::de::Error::custom)?;
+                binds.push(bind);
+            }
+            Ok(binds)
+        }
+    }
+
+    d.deserialize_any(FromListOrTable)
+}
+
+/// Serializes binds ordered by name so that specs with the same binds always render
+/// identically, whatever order the binds were collected in.
+pub fn serialize_binds<S>(binds: &[ServiceBind], s: S) -> result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let mut sorted: Vec<&ServiceBind> = binds.iter().collect();
+    sorted.sort_by_key(|bind| (bind.name.clone(), bind.to_string()));
+    sorted.serialize(s)
+}
+
 pub trait IntoServiceSpec {
     fn into_spec(&self, spec: &mut ServiceSpec);
 
     /// All specs in a composite currently share a lot of the same
     /// information. Here, we create a "base spec" that we can clone and
     /// further customize for each individual service as needed.
+    ///
+    /// Members named in `strategies` use the given update strategy in place of the base spec's.
     fn into_composite_spec(
         &self,
         composite_name: String,
         services: Vec<PackageIdent>,
         bind_map: BindMap,
+        strategies: Option<&HashMap<String, UpdateStrategy>>,
     ) -> Vec<ServiceSpec>;
 
-    fn update_composite(&self, bind_map: &mut BindMap, spec: &mut ServiceSpec);
+    /// Updates a composite member's spec in place, returning any composite binds that target a
+    /// service not present in `member_names`.
+    fn update_composite(
+        &self,
+        bind_map: &mut BindMap,
+        member_names: &HashSet<String>,
+        spec: &mut ServiceSpec,
+    ) -> Vec<ServiceBind>;
 }
 
 impl IntoServiceSpec for protocol::ctl::SvcLoad {
@@ -168,13 +440,15 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
     ///   (We do not set binds here, though, because that requires
     ///   specialized, service-specific handling.)
     /// * For now, all a composite's services will also share the same
-    ///   update strategy and topology, though we may want to revisit
-    ///   this in the future (particularly for topology).
+    ///   topology, though we may want to revisit this in the future.
+    ///   They share the same update strategy too, unless `strategies`
+    ///   overrides it for a member by service name.
     fn into_composite_spec(
         &self,
         composite_name: String,
         services: Vec<PackageIdent>,
         mut bind_map: BindMap,
+        strategies: Option<&HashMap<String, UpdateStrategy>>,
     ) -> Vec<ServiceSpec> {
         // All the service specs will be customized copies of this.
         let mut base_spec = ServiceSpec::default();
@@ -184,11 +458,11 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
         // that's a Windows-only feature, and we don't currently build
         // Windows composites yet. And we don't have a nice way target
         // them on a per-service basis.
-        base_spec.svc_encrypted_password = None;
+        //
         // TODO (CM): Not setting the dev-mode service config_from value
         // because we don't currently have a nice way to target them on a
         // per-service basis.
-        base_spec.config_from = None;
+        base_spec.sanitize_for_production();
 
         let composite_binds = if let Some(ref list) = self.binds {
             let binds: Vec<ServiceBind> = list.binds.clone().into_iter().map(Into::into).collect();
@@ -197,20 +471,22 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
         } else {
             None
         };
-        let mut specs: Vec<ServiceSpec> = Vec::with_capacity(services.len());
-        for service in services {
-            // Customize each service's spec as appropriate
-            let mut spec = base_spec.clone();
-            spec.ident = service;
-            if let Some(ref binds) = composite_binds {
-                set_composite_binds(&mut spec, &mut bind_map, &binds);
-            }
-            specs.push(spec);
-        }
-        specs
+        expand_composite_members(
+            &base_spec,
+            services,
+            &mut bind_map,
+            composite_binds.as_ref(),
+            strategies,
+            None,
+        )
     }
 
-    fn update_composite(&self, bind_map: &mut BindMap, spec: &mut ServiceSpec) {
+    fn update_composite(
+        &self,
+        bind_map: &mut BindMap,
+        member_names: &HashSet<String>,
+        spec: &mut ServiceSpec,
+    ) -> Vec<ServiceBind> {
         // We only want to update fields that were set by SvcLoad
         spec.group = self.group.clone().unwrap_or_default();
         if let Some(ref app_env) = self.application_environment {
@@ -236,12 +512,13 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
                 .collect();
             let (composite, standard) = binds.into_iter().partition(|ref bind| bind.is_composite());
             spec.binds = standard;
-            set_composite_binds(spec, bind_map, &composite);
+            return set_composite_binds(spec, bind_map, &composite, member_names);
         }
+        vec![]
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct ServiceSpec {
     #[serde(
@@ -257,11 +534,16 @@ pub struct ServiceSpec {
     pub application_environment: Option<ApplicationEnvironment>,
     pub bldr_url: String,
     pub channel: String,
+    #[serde(deserialize_with = "deserialize_topology")]
     pub topology: Topology,
+    #[serde(deserialize_with = "deserialize_update_strategy")]
     pub update_strategy: UpdateStrategy,
+    #[serde(deserialize_with = "deserialize_binds", serialize_with = "serialize_binds")]
     pub binds: Vec<ServiceBind>,
     pub binding_mode: BindingMode,
     pub config_from: Option<PathBuf>,
+    // Defaults to `DEFAULT_DESIRED_STATE` when missing, and is always written out so that the
+    // state a spec was loaded with is preserved when it's rewritten.
     #[serde(
         deserialize_with = "deserialize_using_from_str",
         serialize_with = "serialize_using_to_string"
@@ -270,8 +552,74 @@ pub struct ServiceSpec {
     pub svc_encrypted_password: Option<String>,
     // The name of the composite this service is a part of
     pub composite: Option<String>,
+    // When, as an RFC3339 timestamp, and by what means (e.g. "cli" or "migration") this spec was
+    // last written. Older specs lack both.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_loaded_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loaded_from: Option<String>,
+    // Per-bind binding modes, keyed by bind name, which take precedence over `binding_mode`.
+    // A `BTreeMap` keeps the spec hashable and its serialized form stable. This must remain the
+    // last serialized field since it renders as a TOML table.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub binding_mode_overrides: BTreeMap<String, BindingMode>,
+    // Keys from the spec file which this Supervisor doesn't recognize. They're written back out
+    // by `to_file` so that specs written by a newer Supervisor survive being rewritten. They
+    // don't count toward equality, since they can't change how this Supervisor runs the service.
+    #[serde(skip)]
+    pub extra: ExtraFields,
+}
+
+/// The top-level spec file keys which map onto `ServiceSpec` fields.
+const SPEC_FIELD_NAMES: &'static [&'static str] = &[
+    "ident",
+    "group",
+    "application_environment",
+    "bldr_url",
+    "channel",
+    "topology",
+    "update_strategy",
+    "binds",
+    "binding_mode",
+    "config_from",
+    "desired_state",
+    "svc_encrypted_password",
+    "composite",
+    "last_loaded_at",
+    "loaded_from",
+    "binding_mode_overrides",
+    "format_version",
+];
+
+/// Unrecognized top-level keys of a spec file along with their values.
+///
+/// TOML values can't be compared for total equality, so this is compared by the debug rendering
+/// of each value.
+#[derive(Clone, Debug, Default)]
+pub struct ExtraFields(toml::value::Table);
+
+impl ExtraFields {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        self.0.get(key)
+    }
+
+    fn rendered(&self) -> Vec<(&String, String)> {
+        self.0.iter().map(|(k, v)| (k, format!("{:?}", v))).collect()
+    }
+}
+
+impl PartialEq for ExtraFields {
+    fn eq(&self, other: &Self) -> bool {
+        self.rendered() == other.rendered()
+    }
 }
 
+impl Eq for ExtraFields {}
+
 impl ServiceSpec {
     pub fn default_for(ident: PackageIdent) -> Self {
         let mut spec = Self::default();
@@ -279,637 +627,6174 @@ impl ServiceSpec {
         spec
     }
 
-    fn to_toml_string(&self) -> Result<String> {
-        if self.ident == PackageIdent::default() {
-            return Err(sup_error!(Error::MissingRequiredIdent));
+    /// Builds a default spec for an installed package, for when no spec was given. Each required
+    /// bind of the package gets a placeholder bind to the group of the same name in the default
+    /// group, such as `database:database.default`, so the operator can see which binds need
+    /// filling in.
+    ///
+    /// # Errors
+    ///
+    /// * If the package's binds can't be read
+    /// * If a placeholder service group would be invalid
+    pub fn from_package(package: &PackageInstall) -> Result<ServiceSpec> {
+        let mut spec = ServiceSpec::default_for(package.ident().clone());
+        for bind in package.binds()? {
+            let group = ServiceGroup::new(None, &bind.service, DEFAULT_GROUP, None)?;
+            spec.binds.push(ServiceBind {
+                name: bind.service,
+                service_group: group,
+                service_name: None,
+                fallback_service_group: None,
+                port: None,
+            });
         }
-        toml::to_string(self).map_err(|err| sup_error!(Error::ServiceSpecRender(err)))
+        Ok(spec)
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = File::open(&path)
-            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)))?;
-        let mut file = BufReader::new(file);
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)
-            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)))?;
-        Self::from_str(&buf)
+    /// Returns a read-only view which borrows this spec's fields.
+    pub fn as_ref_view(&self) -> SpecRef {
+        SpecRef { spec: self }
     }
 
-    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        debug!(
-            "Writing service spec to '{}': {:?}",
-            path.as_ref().display(),
-            &self
-        );
-        let dst_path = path.as_ref()
-            .parent()
-            .expect("Cannot determine parent directory for service spec");
-        let tmpfile = path.as_ref()
-            .with_extension(thread_rng().gen_ascii_chars().take(8).collect::<String>());
-        fs::create_dir_all(dst_path)
-            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)))?;
-        // Release the write file handle before the end of the function since we're done
-        {
-            let mut file = File::create(&tmpfile)
-                .map_err(|err| sup_error!(Error::ServiceSpecFileIO(tmpfile.to_path_buf(), err)))?;
-            let toml = self.to_toml_string()?;
-            file.write_all(toml.as_bytes())
-                .map_err(|err| sup_error!(Error::ServiceSpecFileIO(tmpfile.to_path_buf(), err)))?;
-        }
-        fs::rename(&tmpfile, path.as_ref())
-            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)))?;
+    /// Returns this spec with its channel replaced.
+    pub fn with_channel(mut self, channel: String) -> Self {
+        self.channel = channel;
+        self
+    }
 
-        Ok(())
+    /// Returns this spec with its topology replaced.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
     }
 
-    pub fn file_name(&self) -> String {
-        format!("{}.{}", &self.ident.name, SPEC_FILE_EXT)
+    /// Returns this spec with its update strategy replaced.
+    pub fn with_update_strategy(mut self, update_strategy: UpdateStrategy) -> Self {
+        self.update_strategy = update_strategy;
+        self
     }
 
-    pub fn validate(&self, package: &PackageInstall) -> Result<()> {
-        self.validate_binds(package)?;
-        Ok(())
+    /// Removes the bind with the given name, returning it if the spec had one.
+    pub fn remove_bind(&mut self, name: &str) -> Option<ServiceBind> {
+        let index = self.binds.iter().position(|bind| bind.name == name)?;
+        Some(self.binds.remove(index))
     }
 
-    /// Validates that all required package binds are present in service binds and all remaining
-    /// service binds are optional package binds.
-    ///
-    /// # Errors
-    ///
-    /// * If any required required package binds are missing in service binds
-    /// * If any given service binds are in neither required nor optional package binds
-    fn validate_binds(&self, package: &PackageInstall) -> Result<()> {
-        let mut svc_binds: HashSet<String> =
-            HashSet::from_iter(self.binds.iter().cloned().map(|b| b.name));
+    /// Removes every bind from the spec.
+    pub fn clear_binds(&mut self) {
+        self.binds.clear();
+    }
 
-        let mut missing_req_binds = Vec::new();
-        // Remove each service bind that matches a required package bind. If a required package
-        // bind is not found, add the bind to the missing list to return an `Err`.
-        for req_bind in package.binds()?.iter().map(|b| &b.service) {
-            if svc_binds.contains(req_bind) {
-                svc_binds.remove(req_bind);
-            } else {
-                missing_req_binds.push(req_bind.clone());
-            }
+    /// The binds whose service group runs the named service, such as every bind to `redis`
+    /// whatever its group.
+    pub fn binds_for_service(&self, service_name: &str) -> Vec<&ServiceBind> {
+        self.binds
+            .iter()
+            .filter(|bind| bind.service_group.service() == service_name)
+            .collect()
+    }
+
+    /// The binds whose service group isn't among `live_groups`, the groups which currently have
+    /// live members. A bind with a fallback service group is satisfied by either group being
+    /// live.
+    pub fn unsatisfied_binds(&self, live_groups: &HashSet<ServiceGroup>) -> Vec<ServiceBind> {
+        self.binds
+            .iter()
+            .filter(|bind| {
+                !live_groups.contains(&bind.service_group)
+                    && !bind.fallback_service_group
+                        .as_ref()
+                        .map_or(false, |group| live_groups.contains(group))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the binding mode for the named bind, preferring an explicit per-bind override and
+    /// falling back to the spec's `binding_mode`.
+    pub fn binding_mode_for(&self, bind_name: &str) -> BindingMode {
+        self.binding_mode_overrides
+            .get(bind_name)
+            .cloned()
+            .unwrap_or(self.binding_mode)
+    }
+
+    /// Whether the `running` service has to be restarted to apply this spec. Only a new
+    /// package ident, topology, or update strategy calls for a restart; other changes, such as
+    /// to binds or the desired state, can be applied to the running service.
+    pub fn requires_restart_relative_to(&self, running: &ServiceSpec) -> bool {
+        self.ident != running.ident
+            || self.topology != running.topology
+            || self.update_strategy != running.update_strategy
+    }
+
+    /// Clears the development-only `config_from` and `svc_encrypted_password` fields so they
+    /// aren't carried into a production spec, returning the fields which had a value.
+    pub fn sanitize_for_production(&mut self) -> Vec<SpecField> {
+        let mut cleared = Vec::new();
+        if self.config_from.take().is_some() {
+            cleared.push(SpecField::ConfigFrom);
         }
-        // If we have missing required binds, return an `Err`.
-        if !missing_req_binds.is_empty() {
-            return Err(sup_error!(Error::MissingRequiredBind(missing_req_binds)));
+        if self.svc_encrypted_password.take().is_some() {
+            cleared.push(SpecField::SvcEncryptedPassword);
         }
+        cleared
+    }
 
-        // Remove each service bind that matches an optional package bind.
-        for opt_bind in package.binds_optional()?.iter().map(|b| &b.service) {
-            if svc_binds.contains(opt_bind) {
-                svc_binds.remove(opt_bind);
+    /// Stamps the spec with the current time and the given `source` of the load, such as
+    /// `LOADED_FROM_CLI`.
+    pub fn record_load(&mut self, source: &str) {
+        self.last_loaded_at = Some(time::now_utc().rfc3339().to_string());
+        self.loaded_from = Some(source.to_string());
+    }
+
+    /// Forgets when and how the spec was last loaded.
+    pub fn clear_load_record(&mut self) {
+        self.last_loaded_at = None;
+        self.loaded_from = None;
+    }
+
+    /// The distinct service groups this spec's binds target, in the order the binds name them.
+    pub fn dependency_groups(&self) -> Vec<ServiceGroup> {
+        let mut groups: Vec<ServiceGroup> = Vec::new();
+        for bind in self.binds.iter() {
+            if !groups.contains(&bind.service_group) {
+                groups.push(bind.service_group.clone());
             }
         }
-        // If we have remaining service binds then they are neither required nor optional package
-        // binds. In this case, return an `Err`.
-        if !svc_binds.is_empty() {
-            return Err(sup_error!(Error::InvalidBinds(
-                svc_binds.into_iter().collect()
-            )));
+        groups
+    }
+
+    /// The arguments to `hab svc load` which would recreate this spec, starting with the ident.
+    /// Options whose value is the default are left out.
+    ///
+    /// NOTE: `config_from` and `svc_encrypted_password` can't be given to `hab svc load`, and
+    /// so aren't represented.
+    pub fn to_load_args(&self) -> Vec<String> {
+        let mut args = vec![self.ident.to_string()];
+        if let Some(ref app_env) = self.application_environment {
+            args.push(String::from("--application"));
+            args.push(app_env.application().to_string());
+            args.push(String::from("--environment"));
+            args.push(app_env.environment().to_string());
+        }
+        if self.group != DEFAULT_GROUP {
+            args.push(String::from("--group"));
+            args.push(self.group.clone());
         }
+        if self.bldr_url != DEFAULT_BLDR_URL {
+            args.push(String::from("--url"));
+            args.push(self.bldr_url.clone());
+        }
+        if self.channel != STABLE_CHANNEL {
+            args.push(String::from("--channel"));
+            args.push(self.channel.clone());
+        }
+        if self.topology != Topology::default() {
+            args.push(String::from("--topology"));
+            args.push(self.topology.to_string());
+        }
+        if self.update_strategy != UpdateStrategy::default() {
+            args.push(String::from("--strategy"));
+            args.push(self.update_strategy.to_string());
+        }
+        for bind in self.binds.iter() {
+            args.push(String::from("--bind"));
+            args.push(bind.to_string());
+        }
+        if self.binding_mode != BindingMode::Strict {
+            args.push(String::from("--binding-mode"));
+            args.push(self.binding_mode.to_string());
+        }
+        args
+    }
 
-        Ok(())
+    /// The group with any `${app}` and `${env}` placeholders replaced by the application and
+    /// environment of the spec's application environment, so that a single spec can be reused
+    /// across environments. A group without placeholders is returned as-is.
+    ///
+    /// # Errors
+    ///
+    /// * If the group has a placeholder and the spec has no application environment
+    /// * If the group has any other placeholder
+    pub fn resolved_group(&self) -> Result<String> {
+        if !self.group.contains("${") {
+            return Ok(self.group.clone());
+        }
+        let app_env = self.application_environment.as_ref();
+        expand_vars(&self.group, |name| match (name, app_env) {
+            ("app", Some(app_env)) => Some(app_env.application().to_string()),
+            ("env", Some(app_env)) => Some(app_env.environment().to_string()),
+            _ => None,
+        }).map_err(|_| sup_error!(Error::UnresolvedGroupTemplate(self.group.clone())))
     }
-}
 
-impl Default for ServiceSpec {
-    fn default() -> Self {
-        ServiceSpec {
-            ident: PackageIdent::default(),
-            group: DEFAULT_GROUP.to_string(),
-            application_environment: None,
-            bldr_url: DEFAULT_BLDR_URL.to_string(),
-            channel: STABLE_CHANNEL.to_string(),
-            topology: Topology::default(),
-            update_strategy: UpdateStrategy::default(),
-            binds: Vec::default(),
-            binding_mode: BindingMode::Strict,
-            config_from: None,
-            desired_state: DesiredState::default(),
-            svc_encrypted_password: None,
-            composite: None,
+    /// Returns a copy of this spec with its group resolved, as a running service reports it. The
+    /// group is left as-is if it can't be resolved.
+    pub fn with_resolved_group(&self) -> ServiceSpec {
+        let mut spec = self.clone();
+        if let Ok(group) = self.resolved_group() {
+            spec.group = group;
         }
+        spec
     }
-}
 
-impl FromStr for ServiceSpec {
-    type Err = SupError;
+    /// The service group this spec's service runs in, built from its application environment,
+    /// package name, and resolved group.
+    ///
+    /// # Errors
+    ///
+    /// * If the group has a placeholder which can't be resolved
+    /// * If the group or application environment don't form a valid service group
+    pub fn service_group(&self) -> Result<ServiceGroup> {
+        self.service_group_named(&self.ident.name)
+    }
 
-    fn from_str(toml: &str) -> result::Result<Self, Self::Err> {
-        let spec: ServiceSpec =
-            toml::from_str(toml).map_err(|e| sup_error!(Error::ServiceSpecParse(e)))?;
-        if spec.ident == PackageIdent::default() {
-            return Err(sup_error!(Error::MissingRequiredIdent));
-        }
-        Ok(spec)
+    /// The service group `service` would run in if it shared this spec's application environment
+    /// and group, as composite members do.
+    ///
+    /// NOTE: Organizations are a Supervisor-wide setting which specs don't record, so the
+    /// returned group never has one.
+    fn service_group_named(&self, service: &str) -> Result<ServiceGroup> {
+        let group = ServiceGroup::new(
+            self.application_environment.as_ref(),
+            service,
+            &self.resolved_group()?,
+            None, // <-- organization
+        )?;
+        Ok(group)
     }
-}
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct ServiceBind {
-    pub name: String,
-    pub service_group: ServiceGroup,
-    pub service_name: Option<String>,
-}
+    /// Whether this spec belongs to a composite.
+    pub fn is_composite_member(&self) -> bool {
+        self.composite_name().is_some()
+    }
 
-impl ServiceBind {
-    pub fn is_composite(&self) -> bool {
-        self.service_name.is_some()
+    /// The name of the composite this spec belongs to, if any. An empty composite name is
+    /// treated the same as an unset one.
+    pub fn composite_name(&self) -> Option<&str> {
+        match self.composite {
+            Some(ref name) if !name.is_empty() => Some(name.as_str()),
+            _ => None,
+        }
     }
-}
 
-impl FromStr for ServiceBind {
-    type Err = SupError;
+    fn to_toml_string(&self) -> Result<String> {
+        if self.ident == PackageIdent::default() {
+            return Err(sup_error!(Error::MissingRequiredIdent));
+        }
+        self.render_toml()
+    }
 
-    fn from_str(bind_str: &str) -> result::Result<Self, Self::Err> {
-        let values: Vec<&str> = bind_str.split(':').collect();
-        if !(values.len() == 3 || values.len() == 2) {
-            return Err(sup_error!(Error::InvalidBinding(bind_str.to_string())));
+    /// Renders the spec as `to_file` would, but with any `svc_encrypted_password` replaced by
+    /// `"<redacted>"`, so that the spec can be logged. The spec itself is left untouched.
+    pub fn to_redacted_toml_string(&self) -> Result<String> {
+        let mut redacted = self.clone();
+        if redacted.svc_encrypted_password.is_some() {
+            redacted.svc_encrypted_password = Some(String::from("<redacted>"));
         }
-        let bind = if values.len() == 3 {
-            ServiceBind {
-                name: values[1].to_string(),
-                service_group: ServiceGroup::from_str(values[2])?,
-                service_name: Some(values[0].to_string()),
-            }
+        redacted.to_toml_string()
+    }
+
+    /// Renders the spec, led by the `format_version` it's written in.
+    fn render_toml(&self) -> Result<String> {
+        let fields = if self.extra.is_empty() {
+            toml::to_string(self)
         } else {
-            ServiceBind {
-                name: values[0].to_string(),
-                service_group: ServiceGroup::from_str(values[1])?,
-                service_name: None,
+            toml::to_string(&self.to_toml_value()?)
+        }.map_err(|err| sup_error!(Error::ServiceSpecRender(err)))?;
+        Ok(format!("format_version = {}\n{}", SPEC_FORMAT_VERSION, fields))
+    }
+
+    /// Renders the spec like `to_file`, but leaving out every field whose value is the default,
+    /// such as the `stable` channel or an empty list of binds. The result is the smallest spec
+    /// file which parses back to an equal spec. Fields are written in name order, apart from
+    /// tables, which come last.
+    pub fn to_minimal_toml_string(&self) -> Result<String> {
+        if self.ident == PackageIdent::default() {
+            return Err(sup_error!(Error::MissingRequiredIdent));
+        }
+        let mut value = self.to_toml_value()?;
+        let defaults = ServiceSpec::default().to_toml_value()?;
+        if let (&mut toml::Value::Table(ref mut table), &toml::Value::Table(ref defaults)) =
+            (&mut value, &defaults)
+        {
+            let redundant: Vec<String> = table
+                .iter()
+                .filter(|&(key, val)| key != "ident" && defaults.get(key) == Some(val))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in redundant {
+                table.remove(&key);
             }
-        };
-        Ok(bind)
+        }
+        toml::to_string(&value).map_err(|err| sup_error!(Error::ServiceSpecRender(err)))
     }
-}
 
-impl fmt::Display for ServiceBind {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if let Some(ref service_name) = self.service_name {
-            write!(f, "{}:{}:{}", service_name, self.name, self.service_group)
-        } else {
-            write!(f, "{}:{}", self.name, self.service_group)
+    /// The spec as a TOML table, including any unrecognized fields it was read with.
+    fn to_toml_value(&self) -> Result<toml::Value> {
+        let mut value =
+            toml::Value::try_from(self).map_err(|err| sup_error!(Error::ServiceSpecRender(err)))?;
+        if let toml::Value::Table(ref mut table) = value {
+            for (key, val) in self.extra.0.iter() {
+                table.entry(key.clone()).or_insert_with(|| val.clone());
+            }
         }
+        Ok(value)
     }
-}
 
-impl<'de> serde::Deserialize<'de> for ServiceBind {
-    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        deserialize_using_from_str(deserializer)
+    /// Returns a hash of the spec's canonical TOML form. Because binds are rendered in name order
+    /// and formatting is normalized, the hash only changes when the spec's content does, so
+    /// comparing it against a previously recorded value reveals whether a spec file was edited.
+    /// The `format_version` and the load record are left out, since they change whenever the
+    /// Supervisor rewrites or loads the spec, not when anyone edits it.
+    pub fn content_hash(&self) -> Result<String> {
+        let mut canonical = self.clone();
+        canonical.clear_load_record();
+        let toml = toml::to_string(&canonical.to_toml_value()?)
+            .map_err(|err| sup_error!(Error::ServiceSpecRender(err)))?;
+        Ok(hash::hash_string(&toml))
     }
-}
 
-impl serde::Serialize for ServiceBind {
+    /// Renders the spec as JSON using the same field names and value formats as the TOML spec
+    /// file, so binds keep their `name:service.group` string form.
+    pub fn to_json_string(&self) -> Result<String> {
+        if self.ident == PackageIdent::default() {
+            return Err(sup_error!(Error::MissingRequiredIdent));
+        }
+        serde_json::to_string(self).map_err(|err| sup_error!(Error::ServiceSpecJsonRender(err)))
+    }
+
+    /// Builds a spec from an already parsed spec file, keeping any keys this Supervisor doesn't
+    /// recognize in `extra`.
+    fn from_toml_value(value: toml::Value) -> Result<Self> {
+        let mut spec: ServiceSpec = value
+            .clone()
+            .try_into()
+            .map_err(|e| sup_error!(Error::ServiceSpecParse(e)))?;
+        if spec.ident == PackageIdent::default() {
+            return Err(sup_error!(Error::MissingRequiredIdent));
+        }
+        if let toml::Value::Table(table) = value {
+            spec.extra = ExtraFields(
+                table
+                    .into_iter()
+                    .filter(|&(ref key, _)| !SPEC_FIELD_NAMES.contains(&key.as_str()))
+                    .collect(),
+            );
+        }
+        Ok(spec)
+    }
+
+    /// Parses a spec from TOML like `from_str`, but rejects keys this Supervisor doesn't
+    /// recognize rather than preserving them, so that a misspelled field is caught.
+    ///
+    /// # Errors
+    ///
+    /// * If the TOML can't be parsed as a spec
+    /// * If the TOML has a key which isn't a spec field, naming the first such key
+    pub fn from_str_strict(toml: &str) -> Result<Self> {
+        let spec = Self::from_str(toml)?;
+        match spec.extra.0.keys().next() {
+            Some(key) => Err(sup_error!(Error::UnknownSpecField(key.clone()))),
+            None => Ok(spec),
+        }
+    }
+
+    /// Parses a spec from TOML like `from_str`, then expands `${VAR}` references in the
+    /// `bldr_url`, `channel`, and `group` fields from the process environment. A literal `$` is
+    /// written as `$$`.
+    ///
+    /// # Errors
+    ///
+    /// * If the TOML can't be parsed as a spec
+    /// * If a referenced environment variable isn't set
+    pub fn from_str_with_env(toml: &str) -> Result<Self> {
+        let mut spec = Self::from_str(toml)?;
+        let lookup = |name: &str| env::var(name).ok();
+        spec.bldr_url = expand_vars(&spec.bldr_url, &lookup)?;
+        spec.channel = expand_vars(&spec.channel, &lookup)?;
+        spec.group = expand_vars(&spec.group, &lookup)?;
+        Ok(spec)
+    }
+
+    /// Parses a spec from JSON produced by `to_json_string`.
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        let spec: ServiceSpec =
+            serde_json::from_str(json).map_err(|e| sup_error!(Error::ServiceSpecJsonParse(e)))?;
+        if spec.ident == PackageIdent::default() {
+            return Err(sup_error!(Error::MissingRequiredIdent));
+        }
+        Ok(spec)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(&path)
+            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)))?;
+        Self::from_reader(BufReader::new(file)).map_err(|e| match e.err {
+            Error::Io(err) => {
+                sup_error!(Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err))
+            }
+            _ => e,
+        })
+    }
+
+    /// Reads and parses a spec from any `Read` source, such as an archive entry or a network
+    /// stream. The spec is parsed according to its `format_version`, as given by
+    /// `detect_spec_version`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut buf = String::new();
+        reader
+            .read_to_string(&mut buf)
+            .map_err(|err| sup_error!(Error::Io(err)))?;
+        let value: toml::Value =
+            toml::from_str(&buf).map_err(|e| sup_error!(Error::ServiceSpecParse(e)))?;
+        match SpecVersion::of(&value) {
+            // Every format so far is read the same way. A format which needs its own
+            // deserializer gets its own arm here.
+            SpecVersion::Legacy => Self::from_toml_value(value),
+            SpecVersion::Versioned(version) if version <= SPEC_FORMAT_VERSION => {
+                Self::from_toml_value(value)
+            }
+            SpecVersion::Versioned(version) => {
+                Err(sup_error!(Error::UnsupportedSpecFormat(version)))
+            }
+        }
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_file(path.as_ref(), String::new())
+    }
+
+    /// Writes the spec as `to_file` does, but the file begins with a comment recording the
+    /// Supervisor version which wrote it and when, as a guide for anyone editing the file by hand.
+    /// The comment is ignored when the spec is read back.
+    pub fn to_file_with_header<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let header = format!(
+            "# Service spec for {}, written by Habitat Supervisor {} at {}.\n\
+             # Fields which are left out take their default values.\n\n",
+            self.ident,
+            VERSION,
+            time::now_utc().rfc3339()
+        );
+        self.write_file(path.as_ref(), header)
+    }
+
+    /// Atomically writes the spec to `path`, after the comment `header`.
+    fn write_file(&self, path: &Path, header: String) -> Result<()> {
+        debug!("Writing service spec to '{}': {:?}", path.display(), &self);
+        let mut toml = header;
+        toml.push_str(&self.to_toml_string()?);
+        write_atomically(path, toml.as_bytes())
+    }
+
+    /// Moves the spec file at `old_path` to the file name for this spec's ident in the same
+    /// directory, as when a package has been renamed. The new file is written atomically before
+    /// the old one is removed, so an interruption never leaves the spec missing from disk.
+    ///
+    /// # Errors
+    ///
+    /// * If there is no file at `old_path`
+    /// * If the new spec file can't be written or the old one can't be removed
+    pub fn rename_on_disk(&self, old_path: &Path) -> Result<()> {
+        fs::metadata(old_path)
+            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(old_path.to_path_buf(), err)))?;
+        let new_path = self.path_in(
+            old_path
+                .parent()
+                .expect("Cannot determine parent directory for service spec"),
+        );
+        self.to_file(&new_path)?;
+        if new_path.as_path() != old_path {
+            fs::remove_file(old_path)
+                .map_err(|err| sup_error!(Error::ServiceSpecFileIO(old_path.to_path_buf(), err)))?;
+        }
+        Ok(())
+    }
+
+    /// Changes only the `desired_state` of the spec file at `path`, as when a service is being
+    /// started or stopped. The spec is read immediately before being rewritten atomically, which
+    /// keeps the window in which a concurrent edit to another field could be lost to a minimum.
+    ///
+    /// # Errors
+    ///
+    /// * If the spec file can't be read, parsed, or written
+    pub fn set_desired_state_on_disk(path: &Path, state: DesiredState) -> Result<()> {
+        let mut spec = Self::from_file(path)?;
+        if spec.desired_state == state {
+            return Ok(());
+        }
+        spec.desired_state = state;
+        write_atomically(path, spec.to_toml_string()?.as_bytes())
+    }
+
+    pub fn file_name(&self) -> String {
+        self.file_name_with_ext(SPEC_FILE_EXT)
+    }
+
+    /// The path this spec's file has in `dir`.
+    pub fn path_in(&self, dir: &Path) -> PathBuf {
+        dir.join(self.file_name())
+    }
+
+    /// The file name for this spec with the extension `ext`, given without a leading dot.
+    pub fn file_name_with_ext(&self, ext: &str) -> String {
+        format!("{}.{}", &self.ident.name, ext)
+    }
+
+    /// Returns every field that differs between this spec and `other`, with values from `self`
+    /// as the old values and values from `other` as the new ones.
+    ///
+    /// Binds are compared as a set, so the same binds in a different order are not a change.
+    /// The encrypted service password is never rendered, only whether it is set.
+    pub fn diff(&self, other: &ServiceSpec) -> Vec<SpecFieldChange> {
+        let mut changes = Vec::new();
+        if self.ident != other.ident {
+            changes.push(SpecFieldChange::Ident(
+                self.ident.to_string(),
+                other.ident.to_string(),
+            ));
+        }
+        if self.group != other.group {
+            changes.push(SpecFieldChange::Group(
+                self.group.clone(),
+                other.group.clone(),
+            ));
+        }
+        if self.application_environment != other.application_environment {
+            changes.push(SpecFieldChange::ApplicationEnvironment(
+                opt_to_string(self.application_environment.as_ref()),
+                opt_to_string(other.application_environment.as_ref()),
+            ));
+        }
+        if self.bldr_url != other.bldr_url {
+            changes.push(SpecFieldChange::BldrUrl(
+                self.bldr_url.clone(),
+                other.bldr_url.clone(),
+            ));
+        }
+        if self.channel != other.channel {
+            changes.push(SpecFieldChange::Channel(
+                self.channel.clone(),
+                other.channel.clone(),
+            ));
+        }
+        if self.topology != other.topology {
+            changes.push(SpecFieldChange::Topology(
+                self.topology.to_string(),
+                other.topology.to_string(),
+            ));
+        }
+        if self.update_strategy != other.update_strategy {
+            changes.push(SpecFieldChange::UpdateStrategy(
+                self.update_strategy.to_string(),
+                other.update_strategy.to_string(),
+            ));
+        }
+        let self_binds: HashSet<ServiceBind> = self.binds.iter().map(|b| b.normalized()).collect();
+        let other_binds: HashSet<ServiceBind> =
+            other.binds.iter().map(|b| b.normalized()).collect();
+        if self_binds != other_binds {
+            changes.push(SpecFieldChange::Binds(
+                binds_to_string(&self.binds),
+                binds_to_string(&other.binds),
+            ));
+        }
+        if self.binding_mode != other.binding_mode {
+            changes.push(SpecFieldChange::BindingMode(
+                self.binding_mode.to_string(),
+                other.binding_mode.to_string(),
+            ));
+        }
+        if self.config_from != other.config_from {
+            changes.push(SpecFieldChange::ConfigFrom(
+                opt_to_string(self.config_from.as_ref().map(|p| p.display())),
+                opt_to_string(other.config_from.as_ref().map(|p| p.display())),
+            ));
+        }
+        if self.desired_state != other.desired_state {
+            changes.push(SpecFieldChange::DesiredState(
+                self.desired_state.to_string(),
+                other.desired_state.to_string(),
+            ));
+        }
+        if self.svc_encrypted_password != other.svc_encrypted_password {
+            let masked = |p: &Option<String>| {
+                if p.is_some() {
+                    String::from("<set>")
+                } else {
+                    String::from("<unset>")
+                }
+            };
+            changes.push(SpecFieldChange::SvcEncryptedPassword(
+                masked(&self.svc_encrypted_password),
+                masked(&other.svc_encrypted_password),
+            ));
+        }
+        if self.composite != other.composite {
+            changes.push(SpecFieldChange::Composite(
+                opt_to_string(self.composite.as_ref()),
+                opt_to_string(other.composite.as_ref()),
+            ));
+        }
+        if self.binding_mode_overrides != other.binding_mode_overrides {
+            let render = |m: &BTreeMap<String, BindingMode>| {
+                m.iter()
+                    .map(|(name, mode)| format!("{}={}", name, mode))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            changes.push(SpecFieldChange::BindingModeOverrides(
+                render(&self.binding_mode_overrides),
+                render(&other.binding_mode_overrides),
+            ));
+        }
+        changes
+    }
+
+    /// Returns the binds which were added and removed going from this spec to `other`, in that
+    /// order. Binds are matched up by name, so a bind whose target changed is in both lists: its
+    /// new form as added and its old form as removed. Binds which target the same service groups
+    /// however they were written are not a change.
+    pub fn bind_diff(&self, other: &ServiceSpec) -> (Vec<ServiceBind>, Vec<ServiceBind>) {
+        let unmatched = |binds: &[ServiceBind], against: &[ServiceBind]| -> Vec<ServiceBind> {
+            binds
+                .iter()
+                .filter(|bind| {
+                    !against.iter().any(|candidate| {
+                        candidate.name == bind.name && candidate.normalized() == bind.normalized()
+                    })
+                })
+                .cloned()
+                .collect()
+        };
+        (
+            unmatched(&other.binds, &self.binds),
+            unmatched(&self.binds, &other.binds),
+        )
+    }
+
+    /// Overwrites only the named fields of this spec with the values from `other`, leaving
+    /// every other field untouched. Merging `SpecField::Binds` replaces the bind list outright
+    /// rather than combining the two.
+    pub fn merge_from(&mut self, other: &ServiceSpec, fields: &[SpecField]) {
+        for field in fields {
+            match *field {
+                SpecField::Ident => self.ident = other.ident.clone(),
+                SpecField::Group => self.group = other.group.clone(),
+                SpecField::ApplicationEnvironment => {
+                    self.application_environment = other.application_environment.clone()
+                }
+                SpecField::BldrUrl => self.bldr_url = other.bldr_url.clone(),
+                SpecField::Channel => self.channel = other.channel.clone(),
+                SpecField::Topology => self.topology = other.topology,
+                SpecField::UpdateStrategy => self.update_strategy = other.update_strategy,
+                SpecField::Binds => self.binds = other.binds.clone(),
+                SpecField::BindingMode => self.binding_mode = other.binding_mode,
+                SpecField::ConfigFrom => self.config_from = other.config_from.clone(),
+                SpecField::DesiredState => self.desired_state = other.desired_state.clone(),
+                SpecField::SvcEncryptedPassword => {
+                    self.svc_encrypted_password = other.svc_encrypted_password.clone()
+                }
+                SpecField::Composite => self.composite = other.composite.clone(),
+                SpecField::BindingModeOverrides => {
+                    self.binding_mode_overrides = other.binding_mode_overrides.clone()
+                }
+            }
+        }
+    }
+
+    pub fn validate(&self, package: &PackageInstall) -> Result<()> {
+        self.validate_with(package, ValidateOptions::default())
+    }
+
+    /// Validates the spec against its package, additionally running any optional checks
+    /// enabled in `opts`.
+    pub fn validate_with(&self, package: &PackageInstall, opts: ValidateOptions) -> Result<()> {
+        self.validate_ident_origin(package)?;
+        self.validate_group()?;
+        self.validate_binds(package)?;
+        self.validate_self_binds()?;
+        self.validate_composite_binds()?;
+        self.validate_channel()?;
+        self.validate_topology_strategy(opts.strict)?;
+        self.validate_config_source(opts.strict)?;
+        if opts.check_config_from {
+            self.validate_config_from()?;
+        }
+        #[cfg(windows)]
+        self.validate_encrypted_password()?;
+        Ok(())
+    }
+
+    /// Runs every check which doesn't need the service's package to be installed, so a spec can
+    /// be checked before its package is available. Problematic topology and update strategy
+    /// combinations are logged rather than rejected, as with `validate`.
+    ///
+    /// # Errors
+    ///
+    /// * If the spec has no ident
+    /// * If the group is blank or contains a service group separator
+    /// * If more than one service bind has the same name
+    /// * If a service bind targets the service's own service group
+    /// * If the spec is a composite member with a bind naming an organization
+    /// * If the channel name contains invalid characters
+    pub fn validate_structure(&self) -> Result<()> {
+        if self.ident == PackageIdent::default() {
+            return Err(sup_error!(Error::MissingRequiredIdent));
+        }
+        self.validate_group()?;
+        self.validate_unique_bind_names()?;
+        self.validate_self_binds()?;
+        self.validate_composite_binds()?;
+        self.validate_channel()?;
+        self.validate_topology_strategy(false)
+    }
+
+    /// Validates that `svc_encrypted_password`, if set, has the shape of a base64-encoded DPAPI
+    /// blob, so that a malformed password is caught before the Windows service manager uses it.
+    ///
+    /// # Errors
+    ///
+    /// * If `svc_encrypted_password` is set to a string which isn't valid base64
+    #[cfg(windows)]
+    fn validate_encrypted_password(&self) -> Result<()> {
+        let password = match self.svc_encrypted_password {
+            Some(ref password) => password,
+            None => return Ok(()),
+        };
+        let data = password.trim_right_matches('=');
+        let well_formed = !data.is_empty()
+            && password.len() % 4 == 0
+            && password.len() - data.len() <= 2
+            && data.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/');
+        if well_formed {
+            Ok(())
+        } else {
+            Err(sup_error!(Error::InvalidEncryptedPassword))
+        }
+    }
+
+    /// Validates that the spec's ident is from the same origin as the given package, since a
+    /// spec copied between origins would otherwise resolve binds and config against the wrong
+    /// package.
+    ///
+    /// # Errors
+    ///
+    /// * If the spec ident's origin differs from the package ident's origin
+    fn validate_ident_origin(&self, package: &PackageInstall) -> Result<()> {
+        if self.ident.origin != package.ident().origin {
+            return Err(sup_error!(Error::IdentOriginMismatch {
+                spec: self.ident.clone(),
+                package: package.ident().clone(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Validates that `config_from`, if set, points at an existing directory. Specs without a
+    /// `config_from` always pass.
+    ///
+    /// # Errors
+    ///
+    /// * If `config_from` is set to a path which doesn't exist or isn't a directory
+    fn validate_config_from(&self) -> Result<()> {
+        match self.config_from {
+            Some(ref path) if !path.is_dir() => {
+                Err(sup_error!(Error::InvalidConfigFrom(path.clone())))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates that all required package binds are present in service binds and all remaining
+    /// service binds are optional package binds.
+    ///
+    /// # Errors
+    ///
+    /// * If more than one service bind has the same name
+    /// * If any required required package binds are missing in service binds
+    /// * If any given service binds are in neither required nor optional package binds
+    fn validate_binds(&self, package: &PackageInstall) -> Result<()> {
+        self.validate_unique_bind_names()?;
+
+        // Report every problem at once so the spec can be fixed in a single pass.
+        let status = self.bind_status(package)?;
+        if !status.missing.is_empty() || !status.extra.is_empty() {
+            return Err(sup_error!(Error::BindValidation {
+                missing: status.missing,
+                invalid: status.extra,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Compares the service binds against the binds of the given package without failing on
+    /// missing or unknown binds, so they can be reported to an operator.
+    pub fn bind_status(&self, package: &PackageInstall) -> Result<BindStatus> {
+        let mut svc_binds: HashSet<String> =
+            HashSet::from_iter(self.binds.iter().cloned().map(|b| b.name));
+        let (required, optional) = package_bind_names(package)?;
+        let mut status = BindStatus::default();
+
+        // Remove each service bind that matches a required package bind. If a required package
+        // bind is not found, add the bind to the missing list.
+        for req_bind in required {
+            if svc_binds.remove(&req_bind) {
+                status.satisfied.push(req_bind);
+            } else {
+                status.missing.push(req_bind);
+            }
+        }
+
+        // Remove each service bind that matches an optional package bind.
+        for opt_bind in optional {
+            if svc_binds.remove(&opt_bind) {
+                status.satisfied.push(opt_bind);
+            }
+        }
+        // Any remaining service binds are neither required nor optional package binds.
+        status.extra = svc_binds.into_iter().collect();
+
+        status.satisfied.sort();
+        status.missing.sort();
+        status.extra.sort();
+        Ok(status)
+    }
+
+    /// Classifies each of the spec's binds, by name, as a required or optional bind of
+    /// `package`, or as one the package doesn't declare. Unlike validation, undeclared binds
+    /// aren't an error, so every bind can be shown to an operator.
+    pub fn classify_binds(&self, package: &PackageInstall) -> Result<HashMap<String, BindKind>> {
+        let (required, optional) = package_bind_names(package)?;
+        Ok(self.binds
+            .iter()
+            .map(|bind| {
+                let kind = if required.contains(&bind.name) {
+                    BindKind::Required
+                } else if optional.contains(&bind.name) {
+                    BindKind::Optional
+                } else {
+                    BindKind::Unknown
+                };
+                (bind.name.clone(), kind)
+            })
+            .collect())
+    }
+
+    /// Checks for topology and update strategy combinations which are known to cause trouble,
+    /// such as an at-once update of every member of a leader topology disrupting the election.
+    /// Problems are logged as warnings unless `strict` is set.
+    ///
+    /// # Errors
+    ///
+    /// * If `strict` is set and the combination is known to be problematic
+    fn validate_topology_strategy(&self, strict: bool) -> Result<()> {
+        match (self.topology, self.update_strategy) {
+            (Topology::Leader, UpdateStrategy::AtOnce) => {
+                if strict {
+                    return Err(sup_error!(Error::IncompatibleTopologyStrategy {
+                        topology: self.topology,
+                        strategy: self.update_strategy,
+                    }));
+                }
+                outputln!(
+                    "Warning: {} uses the {} topology with the {} update strategy, which can \
+                     disrupt leader election during updates",
+                    self.ident,
+                    self.topology,
+                    self.update_strategy
+                );
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the development-only `config_from` isn't combined with a channel other than
+    /// the default, which suggests confusion over where the service's config comes from. The
+    /// conflict is logged as a warning unless `strict` is set.
+    ///
+    /// # Errors
+    ///
+    /// * If `strict` is set and both `config_from` and a non-default channel are set
+    fn validate_config_source(&self, strict: bool) -> Result<()> {
+        let config_from = match self.config_from {
+            Some(ref config_from) => config_from,
+            None => return Ok(()),
+        };
+        if self.channel.is_empty() || self.channel == STABLE_CHANNEL {
+            return Ok(());
+        }
+        if strict {
+            return Err(sup_error!(Error::ConflictingConfigSource(
+                config_from.clone(),
+                self.channel.clone()
+            )));
+        }
+        outputln!(
+            "Warning: {} takes its config from {} but is updated from the {} channel",
+            self.ident,
+            config_from.display(),
+            self.channel
+        );
+        Ok(())
+    }
+
+    /// Validates that no service bind targets the service's own service group, which would leave
+    /// the service waiting on itself.
+    ///
+    /// # Errors
+    ///
+    /// * If the spec's own service group is invalid
+    /// * If any service bind targets the spec's own service group
+    fn validate_self_binds(&self) -> Result<()> {
+        let own_group = self.service_group()?;
+        match self.binds.iter().find(|bind| bind.matches_group(&own_group)) {
+            Some(bind) => Err(sup_error!(Error::SelfBind(bind.name.clone()))),
+            None => Ok(()),
+        }
+    }
+
+    /// Validates that the group isn't blank and doesn't contain any of the `.`, `@`, or `#`
+    /// separators of a service group name, which would otherwise only surface as an invalid
+    /// service group once the service is started.
+    ///
+    /// # Errors
+    ///
+    /// * If the group is empty, whitespace only, or contains a separator
+    fn validate_group(&self) -> Result<()> {
+        let has_separator = self.group.contains(&['.', '@', '#'][..]);
+        if self.group.trim().is_empty() || has_separator {
+            Err(sup_error!(Error::InvalidGroup(self.group.clone())))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Validates that the channel name only uses the characters Builder allows in channel
+    /// names: ASCII letters, digits, dashes, and underscores. An empty channel is allowed, as
+    /// the default channel is applied when the package is installed.
+    ///
+    /// # Errors
+    ///
+    /// * If the channel name contains any other character
+    pub fn validate_channel(&self) -> Result<()> {
+        let valid = self.channel
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Ok(())
+        } else {
+            Err(sup_error!(Error::InvalidChannel(self.channel.clone())))
+        }
+    }
+
+    /// Validates that a composite member has no binds to a service group with an organization,
+    /// which composites don't support. Standalone services may bind to any service group.
+    ///
+    /// # Errors
+    ///
+    /// * If the spec is a composite member and any service bind names an organization
+    fn validate_composite_binds(&self) -> Result<()> {
+        if !self.is_composite_member() {
+            return Ok(());
+        }
+        let org_binds: Vec<String> = self.binds
+            .iter()
+            .filter(|bind| bind.service_group.org().is_some())
+            .map(|bind| bind.to_string())
+            .collect();
+        if org_binds.is_empty() {
+            Ok(())
+        } else {
+            Err(sup_error!(Error::UnsupportedOrgBind(org_binds)))
+        }
+    }
+
+    /// Validates that no two service binds share the same name, regardless of which service
+    /// groups they point to.
+    ///
+    /// # Errors
+    ///
+    /// * If more than one service bind has the same name
+    fn validate_unique_bind_names(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for bind in self.binds.iter() {
+            if !seen.insert(&bind.name) {
+                return Err(sup_error!(Error::DuplicateBind(bind.name.clone())));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Ord for ServiceSpec {
+    /// Orders specs by package ident (origin, name, version, and release, each compared as
+    /// strings) and then by group. The remaining fields only break ties. They're the fields
+    /// `PartialEq` compares, each compared field by field as well, which keeps the ordering
+    /// consistent with `Eq`.
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.ident
+            .origin
+            .cmp(&other.ident.origin)
+            .then_with(|| self.ident.name.cmp(&other.ident.name))
+            .then_with(|| self.ident.version.cmp(&other.ident.version))
+            .then_with(|| self.ident.release.cmp(&other.ident.release))
+            .then_with(|| self.group.cmp(&other.group))
+            .then_with(|| {
+                let env = |spec: &ServiceSpec| {
+                    spec.application_environment.as_ref().map(ToString::to_string)
+                };
+                env(self).cmp(&env(other))
+            })
+            .then_with(|| self.bldr_url.cmp(&other.bldr_url))
+            .then_with(|| self.channel.cmp(&other.channel))
+            .then_with(|| (self.topology as i32).cmp(&(other.topology as i32)))
+            .then_with(|| (self.update_strategy as i32).cmp(&(other.update_strategy as i32)))
+            .then_with(|| cmp_binds(&self.binds, &other.binds))
+            .then_with(|| (self.binding_mode as i32).cmp(&(other.binding_mode as i32)))
+            .then_with(|| self.config_from.cmp(&other.config_from))
+            .then_with(|| self.desired_state.cmp(&other.desired_state))
+            .then_with(|| self.svc_encrypted_password.cmp(&other.svc_encrypted_password))
+            .then_with(|| self.composite.cmp(&other.composite))
+            .then_with(|| {
+                self.binding_mode_overrides
+                    .iter()
+                    .map(|(name, mode)| (name, *mode as i32))
+                    .cmp(
+                        other
+                            .binding_mode_overrides
+                            .iter()
+                            .map(|(name, mode)| (name, *mode as i32)),
+                    )
+            })
+    }
+}
+
+/// Orders lists of binds element by element, comparing each bind's fields in turn. Service
+/// groups are compared as strings, which are equal exactly when the groups are.
+fn cmp_binds(ours: &[ServiceBind], theirs: &[ServiceBind]) -> cmp::Ordering {
+    for (a, b) in ours.iter().zip(theirs.iter()) {
+        let ordering = a.name
+            .cmp(&b.name)
+            .then_with(|| a.service_group.to_string().cmp(&b.service_group.to_string()))
+            .then_with(|| a.service_name.cmp(&b.service_name))
+            .then_with(|| {
+                let fallback = |bind: &ServiceBind| {
+                    bind.fallback_service_group.as_ref().map(ToString::to_string)
+                };
+                fallback(a).cmp(&fallback(b))
+            })
+            .then_with(|| a.port.cmp(&b.port));
+        if ordering != cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+    ours.len().cmp(&theirs.len())
+}
+
+// `extra` and the load record are left out of equality and hashing. Running services keep
+// neither, so counting them would make the spec watcher see such specs as changed, and restart
+// their services, on each spec directory event. Loading an unchanged spec again likewise
+// mustn't look like a change.
+impl PartialEq for ServiceSpec {
+    fn eq(&self, other: &Self) -> bool {
+        self.ident == other.ident
+            && self.group == other.group
+            && self.application_environment == other.application_environment
+            && self.bldr_url == other.bldr_url
+            && self.channel == other.channel
+            && self.topology == other.topology
+            && self.update_strategy == other.update_strategy
+            && self.binds == other.binds
+            && self.binding_mode == other.binding_mode
+            && self.config_from == other.config_from
+            && self.desired_state == other.desired_state
+            && self.svc_encrypted_password == other.svc_encrypted_password
+            && self.composite == other.composite
+            && self.binding_mode_overrides == other.binding_mode_overrides
+    }
+}
+
+impl Eq for ServiceSpec {}
+
+impl Hash for ServiceSpec {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ident.hash(state);
+        self.group.hash(state);
+        self.application_environment.hash(state);
+        self.bldr_url.hash(state);
+        self.channel.hash(state);
+        self.topology.hash(state);
+        self.update_strategy.hash(state);
+        self.binds.hash(state);
+        self.binding_mode.hash(state);
+        self.config_from.hash(state);
+        self.desired_state.hash(state);
+        self.svc_encrypted_password.hash(state);
+        self.composite.hash(state);
+        self.binding_mode_overrides.hash(state);
+    }
+}
+
+impl PartialOrd for ServiceSpec {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Default for ServiceSpec {
+    fn default() -> Self {
+        ServiceSpec {
+            ident: PackageIdent::default(),
+            group: DEFAULT_GROUP.to_string(),
+            application_environment: None,
+            bldr_url: DEFAULT_BLDR_URL.to_string(),
+            channel: STABLE_CHANNEL.to_string(),
+            topology: Topology::default(),
+            update_strategy: UpdateStrategy::default(),
+            binds: Vec::default(),
+            binding_mode: BindingMode::Strict,
+            config_from: None,
+            desired_state: DesiredState::default(),
+            svc_encrypted_password: None,
+            composite: None,
+            last_loaded_at: None,
+            loaded_from: None,
+            binding_mode_overrides: BTreeMap::new(),
+            extra: ExtraFields::default(),
+        }
+    }
+}
+
+/// Renders a compact, multi-line summary of the spec for command line output. Optional fields
+/// are only shown when set.
+impl fmt::Display for ServiceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ident: {}", self.ident)?;
+        write!(f, "\ngroup: {}", self.group)?;
+        if let Some(ref app_env) = self.application_environment {
+            write!(f, "\napplication environment: {}", app_env)?;
+        }
+        write!(f, "\nchannel: {}", self.channel)?;
+        write!(f, "\ntopology: {}", self.topology)?;
+        write!(f, "\nupdate strategy: {}", self.update_strategy)?;
+        write!(f, "\ndesired state: {}", self.desired_state)?;
+        if let Some(name) = self.composite_name() {
+            write!(f, "\ncomposite: {}", name)?;
+        }
+        if let Some(ref config_from) = self.config_from {
+            write!(f, "\nconfig from: {}", config_from.display())?;
+        }
+        if !self.binds.is_empty() {
+            write!(f, "\nbinds:")?;
+            for bind in self.binds.iter() {
+                write!(f, "\n  {}", bind)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A lightweight view of a `ServiceSpec` holding only the fields needed to list services.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ServiceSpecSummary {
+    #[serde(
+        deserialize_with = "deserialize_using_from_str",
+        serialize_with = "serialize_using_to_string"
+    )]
+    pub ident: PackageIdent,
+    pub group: String,
+    #[serde(deserialize_with = "deserialize_topology")]
+    pub topology: Topology,
+    #[serde(
+        deserialize_with = "deserialize_using_from_str",
+        serialize_with = "serialize_using_to_string"
+    )]
+    pub desired_state: DesiredState,
+}
+
+impl<'a> From<&'a ServiceSpec> for ServiceSpecSummary {
+    fn from(spec: &'a ServiceSpec) -> Self {
+        ServiceSpecSummary {
+            ident: spec.ident.clone(),
+            group: spec.group.clone(),
+            topology: spec.topology,
+            desired_state: spec.desired_state.clone(),
+        }
+    }
+}
+
+/// A read-only view of a `ServiceSpec` which borrows its fields rather than cloning them, as
+/// returned by `ServiceSpec::as_ref_view`.
+#[derive(Clone, Copy, Debug)]
+pub struct SpecRef<'a> {
+    spec: &'a ServiceSpec,
+}
+
+impl<'a> SpecRef<'a> {
+    pub fn ident(&self) -> &'a PackageIdent {
+        &self.spec.ident
+    }
+
+    pub fn group(&self) -> &'a str {
+        &self.spec.group
+    }
+
+    pub fn application_environment(&self) -> Option<&'a ApplicationEnvironment> {
+        self.spec.application_environment.as_ref()
+    }
+
+    pub fn bldr_url(&self) -> &'a str {
+        &self.spec.bldr_url
+    }
+
+    pub fn channel(&self) -> &'a str {
+        &self.spec.channel
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.spec.topology
+    }
+
+    pub fn update_strategy(&self) -> UpdateStrategy {
+        self.spec.update_strategy
+    }
+
+    pub fn binds(&self) -> &'a [ServiceBind] {
+        &self.spec.binds
+    }
+
+    pub fn binding_mode(&self) -> BindingMode {
+        self.spec.binding_mode
+    }
+
+    pub fn desired_state(&self) -> &'a DesiredState {
+        &self.spec.desired_state
+    }
+
+    pub fn composite(&self) -> Option<&'a str> {
+        self.spec.composite.as_ref().map(String::as_str)
+    }
+}
+
+/// How a spec's binds line up with the binds of its package, as returned by
+/// `ServiceSpec::bind_status`. Each list holds bind names in sorted order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BindStatus {
+    /// Required or optional package binds which the spec provides.
+    pub satisfied: Vec<String>,
+    /// Required package binds which the spec doesn't provide.
+    pub missing: Vec<String>,
+    /// Spec binds which are neither required nor optional package binds.
+    pub extra: Vec<String>,
+}
+
+/// How a single spec bind relates to the binds of its package, as returned by
+/// `ServiceSpec::classify_binds`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BindKind {
+    /// The package requires the bind.
+    Required,
+    /// The package declares the bind as optional.
+    Optional,
+    /// The package doesn't declare the bind at all.
+    Unknown,
+}
+
+/// Optional checks to run in `ServiceSpec::validate_with`, all of which are disabled by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ValidateOptions {
+    /// Check that `config_from`, when set, is an existing directory. This is a development-only
+    /// setting, so specs without it are unaffected either way.
+    pub check_config_from: bool,
+    /// Treat settings which are known to be problematic, but which a service can still run
+    /// with, as errors rather than logging a warning.
+    pub strict: bool,
+}
+
+/// Builds a `ServiceSpec` one field at a time, starting from the same defaults a spec file
+/// without those fields would get (for example, the "stable" channel).
+#[derive(Debug, Default)]
+pub struct ServiceSpecBuilder {
+    spec: ServiceSpec,
+}
+
+impl ServiceSpecBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ident(mut self, ident: PackageIdent) -> Self {
+        self.spec.ident = ident;
+        self
+    }
+
+    pub fn group<T: Into<String>>(mut self, group: T) -> Self {
+        self.spec.group = group.into();
+        self
+    }
+
+    pub fn application_environment(mut self, app_env: ApplicationEnvironment) -> Self {
+        self.spec.application_environment = Some(app_env);
+        self
+    }
+
+    pub fn bldr_url<T: Into<String>>(mut self, bldr_url: T) -> Self {
+        self.spec.bldr_url = bldr_url.into();
+        self
+    }
+
+    pub fn channel<T: Into<String>>(mut self, channel: T) -> Self {
+        self.spec.channel = channel.into();
+        self
+    }
+
+    pub fn topology(mut self, topology: Topology) -> Self {
+        self.spec.topology = topology;
+        self
+    }
+
+    pub fn update_strategy(mut self, update_strategy: UpdateStrategy) -> Self {
+        self.spec.update_strategy = update_strategy;
+        self
+    }
+
+    pub fn binds(mut self, binds: Vec<ServiceBind>) -> Self {
+        self.spec.binds = binds;
+        self
+    }
+
+    pub fn binding_mode(mut self, binding_mode: BindingMode) -> Self {
+        self.spec.binding_mode = binding_mode;
+        self
+    }
+
+    pub fn config_from<T: Into<PathBuf>>(mut self, config_from: T) -> Self {
+        self.spec.config_from = Some(config_from.into());
+        self
+    }
+
+    pub fn desired_state(mut self, desired_state: DesiredState) -> Self {
+        self.spec.desired_state = desired_state;
+        self
+    }
+
+    /// Returns the built spec.
+    ///
+    /// # Errors
+    ///
+    /// * If no ident was given
+    pub fn build(self) -> Result<ServiceSpec> {
+        if self.spec.ident == PackageIdent::default() {
+            return Err(sup_error!(Error::MissingRequiredIdent));
+        }
+        Ok(self.spec)
+    }
+}
+
+impl FromStr for ServiceSpec {
+    type Err = SupError;
+
+    fn from_str(toml: &str) -> result::Result<Self, Self::Err> {
+        let value: toml::Value =
+            toml::from_str(toml).map_err(|e| sup_error!(Error::ServiceSpecParse(e)))?;
+        Self::from_toml_value(value)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ServiceBind {
+    pub name: String,
+    pub service_group: ServiceGroup,
+    pub service_name: Option<String>,
+    // A service group to bind to instead when `service_group` has no members, written as
+    // `name:primary.group|fallback.group`.
+    pub fallback_service_group: Option<ServiceGroup>,
+    // A port for the consuming service's templates to use instead of the one the bound service
+    // exports, written as a trailing `name:service.group:6380`.
+    pub port: Option<u16>,
+}
+
+impl ServiceBind {
+    pub fn is_composite(&self) -> bool {
+        self.service_name.is_some()
+    }
+
+    /// The name of the composite member this bind is for, or `default` for a standard bind.
+    pub fn service_name_or<'a>(&'a self, default: &'a str) -> &'a str {
+        match self.service_name {
+            Some(ref service_name) => service_name,
+            None => default,
+        }
+    }
+
+    /// Whether this bind targets `group`. The service, group, organization, and application
+    /// environment must all match, so a group with an organization never matches one without.
+    pub fn matches_group(&self, group: &ServiceGroup) -> bool {
+        self.service_group == *group
+    }
+
+    /// Whether the bind's service group is a pattern, such as `redis.*`, which fans out to every
+    /// live service group it matches. A `*` or `?` in the service or group name matches as it
+    /// would in a shell glob.
+    pub fn is_group_pattern(&self) -> bool {
+        is_glob(self.service_group.service()) || is_glob(self.service_group.group())
+    }
+
+    /// Expands a bind whose service group is a pattern into a bind to each of `live_groups` which
+    /// the pattern matches, in service group order. The application environment and organization
+    /// aren't patterns and must match exactly. A bind without a pattern is returned unchanged.
+    pub fn resolve_group_pattern(&self, live_groups: &HashSet<ServiceGroup>) -> Vec<ServiceBind> {
+        if !self.is_group_pattern() {
+            return vec![self.clone()];
+        }
+        let pattern = &self.service_group;
+        let mut groups: Vec<&ServiceGroup> = live_groups
+            .iter()
+            .filter(|group| {
+                group.application_environment() == pattern.application_environment()
+                    && group.org() == pattern.org()
+                    && glob_matches(pattern.service(), group.service())
+                    && glob_matches(pattern.group(), group.group())
+            })
+            .collect();
+        groups.sort_by_key(|group| group.to_string());
+        groups
+            .into_iter()
+            .map(|group| ServiceBind {
+                service_group: group.clone(),
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Returns an equivalent bind in canonical form, so that binds which target the same service
+    /// groups compare equal however they were written:
+    ///
+    /// * Each service group is rebuilt from its application environment, service, group, and
+    ///   organization, giving it the `app.env#service.group@org` form
+    /// * A fallback service group which is the same as the primary service group is dropped,
+    ///   since it could never be used
+    pub fn normalized(&self) -> ServiceBind {
+        let service_group = canonical_service_group(&self.service_group);
+        let fallback_service_group = match self.fallback_service_group {
+            Some(ref fallback) => {
+                let fallback = canonical_service_group(fallback);
+                if fallback == service_group {
+                    None
+                } else {
+                    Some(fallback)
+                }
+            }
+            None => None,
+        };
+        ServiceBind {
+            name: self.name.clone(),
+            service_group: service_group,
+            service_name: self.service_name.clone(),
+            fallback_service_group: fallback_service_group,
+            port: self.port,
+        }
+    }
+
+    /// Parses a bind string like `from_str`, but additionally accepts a `name:service`
+    /// shorthand which binds to `service` in the group, application environment, and
+    /// organization of `default`. A bind with an explicit group is parsed exactly as `from_str`
+    /// would parse it.
+    pub fn from_str_with_default_group(bind_str: &str, default: &ServiceGroup) -> Result<Self> {
+        if bind_str.len() > MAX_BIND_LEN {
+            return Err(sup_error!(Error::InvalidBinding(truncate_bind(bind_str))));
+        }
+        let values = match split_bind_tokens(bind_str, BindSyntax::default().delimiter) {
+            Some(values) => values,
+            None => return Err(sup_error!(Error::InvalidBinding(bind_str.to_string()))),
+        };
+        if values.len() == 2 && !values[1].is_empty() && !values[1].contains('.') {
+            let service_group = ServiceGroup::new(
+                default.application_environment().as_ref(),
+                values[1],
+                default.group(),
+                default.org(),
+            )?;
+            return Ok(ServiceBind {
+                name: values[0].to_string(),
+                service_group: service_group,
+                service_name: None,
+                fallback_service_group: None,
+                port: None,
+            });
+        }
+        Self::from_str(bind_str)
+    }
+}
+
+impl FromStr for ServiceBind {
+    type Err = SupError;
+
+    fn from_str(bind_str: &str) -> result::Result<Self, Self::Err> {
+        Self::parse_with(bind_str, &BindSyntax::default())
+    }
+}
+
+impl ServiceBind {
+    /// Parses a bind string whose tokens are separated by `syntax`'s delimiter rather than `:`.
+    /// As with `from_str`, the final separator may also be written as `=`.
+    pub fn parse_with(bind_str: &str, syntax: &BindSyntax) -> Result<Self> {
+        if bind_str.len() > MAX_BIND_LEN {
+            return Err(sup_error!(Error::InvalidBinding(truncate_bind(bind_str))));
+        }
+        let mut values = match split_bind_tokens(bind_str, syntax.delimiter) {
+            Some(values) => values,
+            None => return Err(sup_error!(Error::InvalidBinding(bind_str.to_string()))),
+        };
+        // A numeric final token is a port, as in `name:service.group:6380`. It can't be confused
+        // with the service group of the composite form `service:name:service.group`, since a
+        // service group always contains a `.`.
+        let port = if values.len() > 2 && is_bind_port(values[values.len() - 1]) {
+            match values.pop().unwrap_or("").parse::<u16>() {
+                Ok(port) => Some(port),
+                Err(_) => return Err(sup_error!(Error::InvalidBinding(bind_str.to_string()))),
+            }
+        } else {
+            None
+        };
+        if !(values.len() == 3 || values.len() == 2) {
+            return Err(sup_error!(Error::InvalidBinding(bind_str.to_string())));
+        }
+        let bind = if values.len() == 3 {
+            let (service_group, fallback) = parse_bind_groups(bind_str, values[2])?;
+            ServiceBind {
+                name: values[1].to_string(),
+                service_group: service_group,
+                service_name: Some(values[0].to_string()),
+                fallback_service_group: fallback,
+                port: port,
+            }
+        } else {
+            let (service_group, fallback) = parse_bind_groups(bind_str, values[1])?;
+            ServiceBind {
+                name: values[0].to_string(),
+                service_group: service_group,
+                service_name: None,
+                fallback_service_group: fallback,
+                port: port,
+            }
+        };
+        Ok(bind)
+    }
+
+    /// Formats the bind like `Display`, but separating its tokens with `syntax`'s delimiter.
+    pub fn format_with(&self, syntax: &BindSyntax) -> String {
+        let mut formatted = String::new();
+        if let Some(ref service_name) = self.service_name {
+            push_bind_name(&mut formatted, service_name, syntax.delimiter);
+            formatted.push(syntax.delimiter);
+        }
+        push_bind_name(&mut formatted, &self.name, syntax.delimiter);
+        formatted.push(syntax.delimiter);
+        formatted.push_str(&self.service_group.to_string());
+        if let Some(ref fallback) = self.fallback_service_group {
+            formatted.push('|');
+            formatted.push_str(&fallback.to_string());
+        }
+        if let Some(port) = self.port {
+            formatted.push(syntax.delimiter);
+            formatted.push_str(&port.to_string());
+        }
+        formatted
+    }
+}
+
+/// The separator between the tokens of a bind string. The default, `:`, is what `from_str` and
+/// `Display` use; tooling which can't easily pass colons through can use another character
+/// with `ServiceBind::parse_with` and `ServiceBind::format_with`.
+///
+/// NOTE: The delimiter must not be a character which can appear in a service group, such as
+/// `.`, `@`, or `#`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BindSyntax {
+    pub delimiter: char,
+}
+
+impl BindSyntax {
+    pub fn new(delimiter: char) -> Self {
+        BindSyntax {
+            delimiter: delimiter,
+        }
+    }
+}
+
+impl Default for BindSyntax {
+    fn default() -> Self {
+        BindSyntax::new(':')
+    }
+}
+
+/// Parses the service group token of `bind_str`, which may name a fallback group after a `|`.
+fn parse_bind_groups(
+    bind_str: &str,
+    groups: &str,
+) -> Result<(ServiceGroup, Option<ServiceGroup>)> {
+    let mut parts = groups.splitn(2, '|');
+    let primary = ServiceGroup::from_str(parts.next().unwrap_or(""))?;
+    match parts.next() {
+        Some(fallback) if fallback.contains('|') => {
+            Err(sup_error!(Error::InvalidBinding(bind_str.to_string())))
+        }
+        Some(fallback) => Ok((primary, Some(ServiceGroup::from_str(fallback)?))),
+        None => Ok((primary, None)),
+    }
+}
+
+/// The names of the required and of the optional binds declared by `package`.
+fn package_bind_names(package: &PackageInstall) -> Result<(HashSet<String>, HashSet<String>)> {
+    let required = package.binds()?.into_iter().map(|b| b.service).collect();
+    let optional = package
+        .binds_optional()?
+        .into_iter()
+        .map(|b| b.service)
+        .collect();
+    Ok((required, optional))
+}
+
+fn is_glob(name: &str) -> bool {
+    name.contains('*') || name.contains('?')
+}
+
+/// Whether `name` matches the glob `pattern`. An invalid pattern matches nothing.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    glob::Pattern::new(pattern)
+        .map(|pattern| pattern.matches(name))
+        .unwrap_or(false)
+}
+
+fn is_bind_port(token: &str) -> bool {
+    !token.is_empty() && token.chars().all(|c| c.is_ascii_digit())
+}
+
+fn canonical_service_group(group: &ServiceGroup) -> ServiceGroup {
+    ServiceGroup::new(
+        group.application_environment().as_ref(),
+        group.service(),
+        group.group(),
+        group.org(),
+    ).unwrap_or_else(|_| group.clone())
+}
+
+/// Shortens an oversized bind string so that it can be reported in an error without echoing the
+/// entire input.
+fn truncate_bind(bind_str: &str) -> String {
+    let mut end = cmp::min(64, bind_str.len());
+    while !bind_str.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &bind_str[..end])
+}
+
+/// Splits a bind string on `delimiter`, normally `:`, treating any `[...]` bracketed segment
+/// (such as an IPv6 literal) as a single token whose contents are preserved verbatim.
+///
+/// A token may also be wrapped in double quotes, as in `"my:name":service.group`, in which case
+/// it runs to the closing quote and is returned without the quotes. This lets names contain
+/// separators. A quote anywhere but the start of a token is an ordinary character.
+///
+/// The separator between the bind name and the service group may be written as `=` instead,
+/// as in `name=service.group` or `service:name=group.default`, for tooling where colons are
+/// awkward to escape. Only that final separator may be `=`, so anything following it is the
+/// service group.
+///
+/// Returns `None` if the brackets or quotes in the string are unbalanced, if a closing quote
+/// isn't followed by a separator, or if `=` is followed by another separator.
+fn split_bind_tokens(bind_str: &str, delimiter: char) -> Option<Vec<&str>> {
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut seen_equals = false;
+    let mut in_quotes = false;
+    let mut after_quotes = false;
+    for (i, c) in bind_str.char_indices() {
+        if in_quotes {
+            if c == '"' {
+                in_quotes = false;
+                after_quotes = true;
+            }
+            continue;
+        }
+        if after_quotes && c != delimiter && c != '=' {
+            return None;
+        }
+        match c {
+            '"' if depth == 0 && i == start => in_quotes = true,
+            '[' => depth += 1,
+            ']' => {
+                if depth == 0 {
+                    return None;
+                }
+                depth -= 1;
+            }
+            c if (c == delimiter || c == '=') && depth == 0 => {
+                if seen_equals {
+                    return None;
+                }
+                seen_equals = c == '=';
+                tokens.push(unquote_bind_token(&bind_str[start..i]));
+                start = i + 1;
+                after_quotes = false;
+            }
+            _ => (),
+        }
+    }
+    if depth != 0 || in_quotes {
+        return None;
+    }
+    tokens.push(unquote_bind_token(&bind_str[start..]));
+    Some(tokens)
+}
+
+fn unquote_bind_token(token: &str) -> &str {
+    if token.len() >= 2 && token.starts_with('"') && token.ends_with('"') {
+        &token[1..token.len() - 1]
+    } else {
+        token
+    }
+}
+
+/// Appends a bind or service name, quoting it if it wouldn't otherwise parse back as one token.
+fn push_bind_name(out: &mut String, name: &str, delimiter: char) {
+    match split_bind_tokens(name, delimiter) {
+        Some(ref tokens) if tokens.len() == 1 && tokens[0] == name => out.push_str(name),
+        _ => {
+            out.push('"');
+            out.push_str(name);
+            out.push('"');
+        }
+    }
+}
+
+impl fmt::Display for ServiceBind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format_with(&BindSyntax::default()))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ServiceBind {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_using_from_str(deserializer)
+    }
+}
+
+impl serde::Serialize for ServiceBind {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Iterates over the service specs in `dir`, parsing each file only when the iterator reaches
+/// it, so a search can stop without reading the rest of the directory. A file which can't be
+/// read or parsed yields an error for that item alone, and iteration carries on past it.
+///
+/// # Errors
+///
+/// * If `dir` can't be read
+pub fn spec_iter(dir: &Path) -> Result<impl Iterator<Item = Result<ServiceSpec>>> {
+    Ok(SpecWatcher::spec_files(dir)?
+        .into_iter()
+        .map(|spec_file| ServiceSpec::from_file(spec_file)))
+}
+
+/// Loads the spec for the service called `name` from `dir`, reading only its `name.spec` file
+/// rather than every spec in the directory. Returns `None` if there is no such file.
+///
+/// # Errors
+///
+/// * If the spec file exists but can't be read or parsed
+pub fn load_spec_by_name(dir: &Path, name: &str) -> Result<Option<ServiceSpec>> {
+    let spec_file = dir.join(format!("{}.{}", name, SPEC_FILE_EXT));
+    match ServiceSpec::from_file(&spec_file) {
+        Ok(spec) => Ok(Some(spec)),
+        Err(e) => match e.err {
+            Error::ServiceSpecFileIO(_, ref err) if err.kind() == io::ErrorKind::NotFound => {
+                Ok(None)
+            }
+            _ => Err(e),
+        },
+    }
+}
+
+/// Whether a running service loaded from the spec `from` can take on the spec `to` without
+/// being restarted. That's only the case when the specs differ in nothing but their binds and
+/// desired state, which can be applied to a running service. Unlike
+/// `ServiceSpec::requires_restart_relative_to`, a change to any other field, such as the group
+/// or channel, rules out reconfiguring in place.
+pub fn can_reconfigure_in_place(from: &ServiceSpec, to: &ServiceSpec) -> bool {
+    from.diff(to).iter().all(|change| match *change {
+        SpecFieldChange::Binds(_, _) | SpecFieldChange::DesiredState(_, _) => true,
+        _ => false,
+    })
+}
+
+/// The specs whose desired state is `Up`, which the Supervisor should start.
+pub fn specs_to_start(specs: &[ServiceSpec]) -> Vec<&ServiceSpec> {
+    specs
+        .iter()
+        .filter(|spec| spec.desired_state == DesiredState::Up)
+        .collect()
+}
+
+/// The specs whose desired state is anything other than `Up`, which the Supervisor should
+/// leave stopped. Paused services are included, as they're never started on their own.
+pub fn specs_to_stop(specs: &[ServiceSpec]) -> Vec<&ServiceSpec> {
+    specs
+        .iter()
+        .filter(|spec| spec.desired_state != DesiredState::Up)
+        .collect()
+}
+
+/// Loads the service specs in `specs_path` whose service group, built from the spec's
+/// application environment, package name, and group, is `group`. Specs don't record an
+/// organization, so a `group` with an organization never matches.
+///
+/// # Errors
+///
+/// * If `specs_path` can't be read
+/// * If any spec file can't be read or parsed, or has an invalid group
+pub fn specs_in_group(specs_path: &Path, group: &ServiceGroup) -> Result<Vec<ServiceSpec>> {
+    let mut specs = Vec::new();
+    for spec_file in SpecWatcher::spec_files(specs_path)? {
+        let spec = ServiceSpec::from_file(&spec_file)?;
+        if spec.service_group()? == *group {
+            specs.push(spec);
+        }
+    }
+    Ok(specs)
+}
+
+/// Loads the service specs in `dir` whose ident matches `pattern`. The pattern is either an
+/// origin followed by `/*`, such as `core/*`, which matches every spec from that origin, or a
+/// partial package ident, such as `core/redis`, which matches specs whose ident satisfies it.
+///
+/// # Errors
+///
+/// * If `pattern` is neither an origin wildcard nor a valid package ident
+/// * If `dir` can't be read
+/// * If any spec file can't be read or parsed
+pub fn specs_matching(dir: &Path, pattern: &str) -> Result<Vec<ServiceSpec>> {
+    let is_match: Box<Fn(&PackageIdent) -> bool> = if pattern.ends_with("/*") {
+        let origin = pattern[..pattern.len() - 2].to_string();
+        Box::new(move |ident| ident.origin == origin)
+    } else {
+        let wanted = PackageIdent::from_str(pattern)?;
+        Box::new(move |ident| ident.satisfies(&wanted))
+    };
+    let mut specs = Vec::new();
+    for spec_file in SpecWatcher::spec_files(dir)? {
+        let spec = ServiceSpec::from_file(&spec_file)?;
+        if is_match(&spec.ident) {
+            specs.push(spec);
+        }
+    }
+    Ok(specs)
+}
+
+/// Loads every spec in `dir`, returning the standalone specs separately from the specs of
+/// composite members, which are keyed by the name of their composite. Both keep the order of
+/// the spec files. Unlike `Spec::load_all`, the composites' own spec files aren't consulted.
+///
+/// # Errors
+///
+/// * If `dir` is locked by another process
+/// * If `dir` can't be read
+/// * If any spec file can't be read or parsed
+pub fn load_grouped(dir: &Path) -> Result<(Vec<ServiceSpec>, HashMap<String, Vec<ServiceSpec>>)> {
+    let mut standalone = Vec::new();
+    let mut members: HashMap<String, Vec<ServiceSpec>> = HashMap::new();
+    let (_lock, spec_files) = SpecWatcher::spec_files_locked(dir)?;
+    for spec_file in spec_files {
+        let spec = ServiceSpec::from_file(&spec_file)?;
+        match spec.composite_name().map(String::from) {
+            Some(name) => members.entry(name).or_insert_with(Vec::new).push(spec),
+            None => standalone.push(spec),
+        }
+    }
+    Ok((standalone, members))
+}
+
+/// Loads every spec file in `dir` and runs `ServiceSpec::validate_structure` on it, returning
+/// the result for each file rather than stopping at the first failure. A file which can't be read
+/// or parsed is reported with that error. If `dir` itself can't be read, the only result is that
+/// error, reported against `dir`.
+pub fn validate_spec_dir(dir: &Path) -> Vec<(PathBuf, Result<()>)> {
+    let spec_files = match SpecWatcher::spec_files(dir) {
+        Ok(spec_files) => spec_files,
+        Err(err) => return vec![(dir.to_path_buf(), Err(err))],
+    };
+    spec_files
+        .into_iter()
+        .map(|spec_file| {
+            let result =
+                ServiceSpec::from_file(&spec_file).and_then(|spec| spec.validate_structure());
+            (spec_file, result)
+        })
+        .collect()
+}
+
+/// The format of a spec file, as recorded by its `format_version` key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SpecVersion {
+    /// Written before spec files recorded a format version.
+    Legacy,
+    Versioned(i64),
+}
+
+impl SpecVersion {
+    /// The format of the parsed spec file `value`, as `detect_spec_version` determines it.
+    fn of(value: &toml::Value) -> Self {
+        match value.get("format_version").and_then(|version| version.as_integer()) {
+            Some(version) => SpecVersion::Versioned(version),
+            None => SpecVersion::Legacy,
+        }
+    }
+}
+
+/// Determines the format of the spec file content `toml` from its `format_version` key. Content
+/// without the key, or which isn't valid TOML, is `SpecVersion::Legacy`; in the latter case
+/// parsing the spec reports the error. A `format_version` which isn't an integer is ignored.
+pub fn detect_spec_version(toml: &str) -> SpecVersion {
+    match toml::from_str(toml) {
+        Ok(value) => SpecVersion::of(&value),
+        Err(_) => SpecVersion::Legacy,
+    }
+}
+
+/// A field which was defaulted or adjusted while migrating a spec file to the current format.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MigrationNote {
+    pub field: SpecField,
+    pub message: String,
+}
+
+impl fmt::Display for MigrationNote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Parses the content of a spec file like `ServiceSpec::from_str`, additionally defaulting an
+/// empty `group`, `bldr_url`, or `channel`, none of which a service can run with. Alongside the
+/// spec, returns a note for each field which was missing from the file and took its default,
+/// and for each field which was adjusted, so that a migration can be audited.
+///
+/// # Errors
+///
+/// * If `toml` can't be parsed as a service spec
+pub fn migrate_spec(toml: &str) -> Result<(ServiceSpec, Vec<MigrationNote>)> {
+    let mut spec = ServiceSpec::from_str(toml)?;
+    let value: toml::Value =
+        toml::from_str(toml).map_err(|e| sup_error!(Error::ServiceSpecParse(e)))?;
+    let mut notes = Vec::new();
+
+    let defaulted = [
+        (SpecField::Group, "group", spec.group.clone()),
+        (SpecField::BldrUrl, "bldr_url", spec.bldr_url.clone()),
+        (SpecField::Channel, "channel", spec.channel.clone()),
+        (SpecField::Topology, "topology", spec.topology.to_string()),
+        (SpecField::UpdateStrategy, "update_strategy", spec.update_strategy.to_string()),
+        (SpecField::BindingMode, "binding_mode", spec.binding_mode.to_string()),
+        (SpecField::DesiredState, "desired_state", spec.desired_state.to_string()),
+    ];
+    for &(field, key, ref default) in defaulted.iter() {
+        if value.get(key).is_none() {
+            notes.push(MigrationNote {
+                field: field,
+                message: format!("{} was not set, defaulted to '{}'", key, default),
+            });
+        }
+    }
+
+    default_if_empty(&mut spec.group, DEFAULT_GROUP, SpecField::Group, "group", &mut notes);
+    default_if_empty(
+        &mut spec.bldr_url,
+        DEFAULT_BLDR_URL,
+        SpecField::BldrUrl,
+        "bldr_url",
+        &mut notes,
+    );
+    default_if_empty(&mut spec.channel, STABLE_CHANNEL, SpecField::Channel, "channel", &mut notes);
+    Ok((spec, notes))
+}
+
+fn default_if_empty(
+    value: &mut String,
+    default: &str,
+    field: SpecField,
+    key: &str,
+    notes: &mut Vec<MigrationNote>,
+) {
+    if value.is_empty() {
+        *value = default.to_string();
+        notes.push(MigrationNote {
+            field: field,
+            message: format!("{} was empty, defaulted to '{}'", key, default),
+        });
+    }
+}
+
+/// Rewrites each spec file in `dir` whose spec `migrate_spec` changes, such as those written by
+/// an older Supervisor with fields it left blank. The decision is made on the parsed specs, so a
+/// file which is already current is left untouched however it's formatted, and keeps any
+/// comments or header. Each rewrite is atomic, so an interrupted migration
+/// leaves every spec either in its old or its new form. The directory is locked with a
+/// `SpecDirLock` for the duration. Returns the paths of the rewritten files.
+///
+/// # Errors
+///
+/// * If `dir` is locked by another process
+/// * If `dir` can't be read
+/// * If any spec file can't be read, parsed, or written
+pub fn migrate_spec_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let _lock = SpecDirLock::acquire(dir)?;
+    let mut migrated = Vec::new();
+    for spec_file in SpecWatcher::spec_files(dir)? {
+        let mut current = String::new();
+        File::open(&spec_file)
+            .and_then(|mut file| file.read_to_string(&mut current))
+            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(spec_file.clone(), err)))?;
+        let (mut spec, notes) = migrate_spec(&current)?;
+        if spec != ServiceSpec::from_str(&current)? {
+            spec.record_load(LOADED_FROM_MIGRATION);
+            let latest = spec.to_toml_string()?;
+            debug!("Migrating service spec '{}'", spec_file.display());
+            for note in notes {
+                outputln!("Migrating {}: {}", spec_file.display(), note);
+            }
+            write_atomically(&spec_file, latest.as_bytes())?;
+            migrated.push(spec_file);
+        }
+    }
+    Ok(migrated)
+}
+
+/// Rewrites each spec in `dir` whose binds aren't sorted by name in its canonical form, as
+/// `to_file` would write it. The decision is made on the parsed specs, so other formatting, and
+/// any comments or header, don't cause a rewrite, and normalizing a directory twice rewrites
+/// nothing the second time. Files are written atomically and the directory is
+/// locked with a `SpecDirLock` for the duration. Returns the paths of the rewritten files.
+///
+/// # Errors
+///
+/// * If `dir` is locked by another process
+/// * If `dir` can't be read
+/// * If any spec file can't be read, parsed, or written
+pub fn normalize_spec_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let _lock = SpecDirLock::acquire(dir)?;
+    let mut normalized = Vec::new();
+    for spec_file in SpecWatcher::spec_files(dir)? {
+        let mut current = String::new();
+        File::open(&spec_file)
+            .and_then(|mut file| file.read_to_string(&mut current))
+            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(spec_file.clone(), err)))?;
+        let spec = ServiceSpec::from_str(&current)?;
+        let canonical = spec.to_toml_string()?;
+        if ServiceSpec::from_str(&canonical)? != spec {
+            debug!("Normalizing service spec '{}'", spec_file.display());
+            write_atomically(&spec_file, canonical.as_bytes())?;
+            normalized.push(spec_file);
+        }
+    }
+    Ok(normalized)
+}
+
+/// Writes `content` to a randomly-named temporary file alongside `path` and then renames it into
+/// place, so readers of `path` only ever observe the old or the new complete content.
+///
+/// `fs::rename` replaces an existing file on every platform, using `MOVEFILE_REPLACE_EXISTING`
+/// on Windows, so a rename refused with `AlreadyExists` means the destination is briefly held by
+/// another process. The rename is retried a few times in that case. The destination is never
+/// removed first, since a reader could then find no spec at all.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let dst_path = path.parent()
+        .expect("Cannot determine parent directory for service spec");
+    let tmpfile = path.with_extension(thread_rng().gen_ascii_chars().take(8).collect::<String>());
+    fs::create_dir_all(dst_path)
+        .map_err(|err| sup_error!(Error::ServiceSpecFileIO(path.to_path_buf(), err)))?;
+    // Release the write file handle before the end of the function since we're done
+    {
+        let mut file = File::create(&tmpfile)
+            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(tmpfile.to_path_buf(), err)))?;
+        file.write_all(content)
+            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(tmpfile.to_path_buf(), err)))?;
+        file.sync_all()
+            .map_err(|err| sup_error!(Error::ServiceSpecFileIO(tmpfile.to_path_buf(), err)))?;
+    }
+    let mut retries = 0;
+    loop {
+        match fs::rename(&tmpfile, path) {
+            Ok(()) => return Ok(()),
+            Err(ref err)
+                if err.kind() == io::ErrorKind::AlreadyExists && retries < RENAME_RETRIES =>
+            {
+                retries += 1;
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(err) => {
+                let _ = fs::remove_file(&tmpfile);
+                return Err(sup_error!(Error::ServiceSpecFileIO(path.to_path_buf(), err)));
+            }
+        }
+    }
+}
+
+/// Expands `${VAR}` references in `value` using `lookup`, with `$$` standing in for a literal
+/// `$`. Any other `$`, including an unterminated `${`, is kept as-is.
+fn expand_vars<F>(value: &str, lookup: F) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(i) = rest.find('$') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with("$$") {
+            expanded.push('$');
+            rest = &rest[2..];
+        } else if rest.starts_with("${") {
+            match rest.find('}') {
+                Some(end) => {
+                    let name = &rest[2..end];
+                    match lookup(name) {
+                        Some(val) => expanded.push_str(&val),
+                        None => return Err(sup_error!(Error::UndefinedSpecVar(name.to_string()))),
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    expanded.push_str(rest);
+                    rest = "";
+                }
+            }
+        } else {
+            expanded.push('$');
+            rest = &rest[1..];
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn opt_to_string<T: fmt::Display>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Renders binds in sorted order so that the output doesn't depend on the order of the `Vec`.
+fn binds_to_string(binds: &[ServiceBind]) -> String {
+    let mut binds: Vec<String> = binds.iter().map(ToString::to_string).collect();
+    binds.sort();
+    binds.join(", ")
+}
+
+/// Reads a `BindMap` from a TOML file. Each table is keyed by the ident of a composite member
+/// and maps that member's bind names to the ident of the service which satisfies them:
+///
+/// ```toml
+/// ["core/web"]
+/// database = "core/postgresql"
+/// ```
+///
+/// # Errors
+///
+/// * If the file can't be read
+/// * If the file isn't valid TOML of the above shape
+/// * If any table key or satisfying service isn't a valid package identifier
+pub fn load_bind_map<P: AsRef<Path>>(path: P) -> Result<BindMap> {
+    let path = path.as_ref();
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|err| sup_error!(Error::BadBindMapFile(path.to_path_buf(), err)))?;
+    let raw: BTreeMap<String, BTreeMap<String, String>> = toml::from_str(&content)
+        .map_err(|err| sup_error!(Error::InvalidBindMap(path.to_path_buf(), err.to_string())))?;
+    let parse_ident = |ident: &str| {
+        PackageIdent::from_str(ident).map_err(|err| {
+            sup_error!(Error::InvalidBindMap(
+                path.to_path_buf(),
+                format!("'{}' is not a valid package identifier, {}", ident, err),
+            ))
+        })
+    };
+    let mut bind_map = BindMap::new();
+    for (ident, binds) in raw {
+        let mut mappings = Vec::with_capacity(binds.len());
+        for (bind_name, satisfying_service) in binds {
+            mappings.push(BindMapping {
+                bind_name: bind_name,
+                satisfying_service: parse_ident(&satisfying_service)?,
+            });
+        }
+        bind_map.insert(parse_ident(&ident)?, mappings);
+    }
+    Ok(bind_map)
+}
+
+/// Looks up the bind mappings in `bind_map` for the service `ident`. An exact match for `ident`
+/// always wins. Failing that, a key which differs from `ident` only in being more or less
+/// qualified is used, so a map keyed by `origin/name` still finds the mappings for a
+/// fully-qualified ident and vice versa. If several keys qualify, the lowest in string order is
+/// used, so the result doesn't depend on the map's iteration order.
+pub fn bind_mappings_for<'a>(
+    bind_map: &'a BindMap,
+    ident: &PackageIdent,
+) -> Option<&'a Vec<BindMapping>> {
+    bind_map_key(bind_map, ident).and_then(|key| bind_map.get(&key))
+}
+
+/// The key of `bind_map` which `bind_mappings_for` uses for `ident`.
+fn bind_map_key(bind_map: &BindMap, ident: &PackageIdent) -> Option<PackageIdent> {
+    if bind_map.contains_key(ident) {
+        return Some(ident.clone());
+    }
+    bind_map
+        .keys()
+        .filter(|key| ident.satisfies(*key) || key.satisfies(ident))
+        .min_by_key(|key| key.to_string())
+        .cloned()
+}
+
+/// Expands a composite into a spec for each of its `services`, each a copy of `base` with the
+/// member's ident. Unlike `IntoServiceSpec::into_composite_spec`, `base` is prepared by the
+/// caller, so one base can be reused to expand many composites.
+///
+/// As in `into_composite_spec`, the development-only fields of `base` aren't carried over, and
+/// `binds` plays the part of the binds given to `hab svc load`. When `binds` is given, each
+/// member's binds are generated from `bind_map` together with the composite binds in `binds`
+/// aimed at that member. When it's `None`, members keep the binds of `base`.
+pub fn expand_composite(
+    base: &ServiceSpec,
+    composite_name: &str,
+    services: Vec<PackageIdent>,
+    mut bind_map: BindMap,
+    binds: Option<&[ServiceBind]>,
+) -> Vec<ServiceSpec> {
+    expand_composite_members(
+        &composite_base(base, composite_name),
+        services,
+        &mut bind_map,
+        composite_binds(binds).as_ref(),
+        None,
+        None,
+    )
+}
+
+/// Expands a composite as `expand_composite` does, but members named in `overrides` have the
+/// fields set there in place of the base spec's.
+///
+/// # Errors
+///
+/// * If a member's group is set to anything but the base spec's group, since every member of a
+///   composite must share its group
+pub fn expand_composite_with_overrides(
+    base: &ServiceSpec,
+    composite_name: &str,
+    services: Vec<PackageIdent>,
+    mut bind_map: BindMap,
+    binds: Option<&[ServiceBind]>,
+    overrides: &HashMap<String, MemberOverrides>,
+) -> Result<Vec<ServiceSpec>> {
+    for (member, member_overrides) in overrides.iter() {
+        if let MemberValue::Set(ref group) = member_overrides.group {
+            if *group != base.group {
+                return Err(sup_error!(Error::MemberGroupOverride {
+                    composite: composite_name.to_string(),
+                    member: member.clone(),
+                    group: group.clone(),
+                }));
+            }
+        }
+    }
+    Ok(expand_composite_members(
+        &composite_base(base, composite_name),
+        services,
+        &mut bind_map,
+        composite_binds(binds).as_ref(),
+        None,
+        Some(overrides),
+    ))
+}
+
+/// Prepares `base` to be copied for each member of the composite `composite_name`.
+fn composite_base(base: &ServiceSpec, composite_name: &str) -> ServiceSpec {
+    let mut base_spec = base.clone();
+    base_spec.composite = Some(composite_name.to_string());
+    base_spec.sanitize_for_production();
+    base_spec.clear_load_record();
+    base_spec
+}
+
+/// The composite binds among `binds`, as `into_composite_spec` picks them out of the binds given
+/// to `hab svc load`.
+fn composite_binds(binds: Option<&[ServiceBind]>) -> Option<Vec<ServiceBind>> {
+    binds.map(|binds| {
+        binds
+            .iter()
+            .filter(|bind| bind.is_composite())
+            .cloned()
+            .collect()
+    })
+}
+
+/// The value of `MemberValue::Inherit` when written as a string.
+pub const INHERIT_MEMBER_VALUE: &'static str = "inherit";
+
+/// A composite member's value for a field of the composite's base spec. Inheriting the base
+/// spec's value is distinct from explicitly setting a value, even an empty one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MemberValue {
+    Inherit,
+    Set(String),
+}
+
+impl Default for MemberValue {
+    fn default() -> Self {
+        MemberValue::Inherit
+    }
+}
+
+impl<'a> From<&'a str> for MemberValue {
+    /// Reads `"inherit"` as `Inherit` and anything else, including the empty string, as that
+    /// value.
+    fn from(value: &'a str) -> Self {
+        if value == INHERIT_MEMBER_VALUE {
+            MemberValue::Inherit
+        } else {
+            MemberValue::Set(value.to_string())
+        }
+    }
+}
+
+impl MemberValue {
+    fn apply_to(&self, field: &mut String) {
+        if let MemberValue::Set(ref value) = *self {
+            *field = value.clone();
+        }
+    }
+}
+
+/// The fields a composite member sets for itself rather than inheriting from the base spec. A
+/// member may only set `group` to the base spec's group, since every member of a composite shares
+/// its group.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemberOverrides {
+    pub group: MemberValue,
+    pub channel: MemberValue,
+}
+
+impl MemberOverrides {
+    fn apply_to(&self, spec: &mut ServiceSpec) {
+        self.group.apply_to(&mut spec.group);
+        self.channel.apply_to(&mut spec.channel);
+    }
+}
+
+/// Clones `base_spec` for each of the composite's `services`, customizing each clone for its
+/// service. When `composite_binds` is given, each member's binds are generated from `bind_map`
+/// together with the composite binds aimed at that member. Members named in `strategies` or
+/// `overrides` are then customized further.
+fn expand_composite_members(
+    base_spec: &ServiceSpec,
+    services: Vec<PackageIdent>,
+    bind_map: &mut BindMap,
+    composite_binds: Option<&Vec<ServiceBind>>,
+    strategies: Option<&HashMap<String, UpdateStrategy>>,
+    overrides: Option<&HashMap<String, MemberOverrides>>,
+) -> Vec<ServiceSpec> {
+    let member_names: HashSet<String> = services.iter().map(|s| s.name.clone()).collect();
+    let mut unknown_binds = Vec::new();
+    let mut specs: Vec<ServiceSpec> = Vec::with_capacity(services.len());
+    for service in services {
+        // Customize each service's spec as appropriate
+        let mut spec = base_spec.clone();
+        spec.ident = service;
+        if let Some(strategy) = strategies.and_then(|s| s.get(&spec.ident.name)) {
+            spec.update_strategy = *strategy;
+        }
+        if let Some(binds) = composite_binds {
+            unknown_binds = set_composite_binds(&mut spec, bind_map, binds, &member_names);
+        }
+        if let Some(member_overrides) = overrides.and_then(|o| o.get(&spec.ident.name)) {
+            member_overrides.apply_to(&mut spec);
+        }
+        specs.push(spec);
+    }
+    warn_unknown_composite_binds(base_spec, &unknown_binds);
+    specs
+}
+
+/// Generate the binds for a composite's service, taking into account
+/// both the values laid out in composite definition and any CLI value
+/// the user may have specified. This allows the user to override a
+/// composite-defined bind, but also (perhaps more usefully) to
+/// declare binds for services within the composite that are not
+/// themselves *satisfied* by other members of the composite.
+///
+/// The final list of bind mappings is generated and then set in the
+/// `ServiceSpec`. Any binds that may have been present in the spec
+/// before are completely ignored.
+///
+/// # Parameters
+///
+/// * bind_map: output of package.bind_map()
+/// * cli_binds: per-service overrides given on the CLI
+/// * member_names: the names of every service in the composite
+///
+/// Returns the composite binds whose service name doesn't match any
+/// member of the composite; these are most likely typos and would
+/// otherwise be silently ignored.
+fn set_composite_binds(
+    spec: &mut ServiceSpec,
+    bind_map: &mut BindMap,
+    binds: &Vec<ServiceBind>,
+    member_names: &HashSet<String>,
+) -> Vec<ServiceBind> {
+    // We'll be layering bind specifications from the composite
+    // with any additional ones from the CLI.
+    let mut composite_binds = Vec::new();
+
+    // First, generate the binds from the composite
+    let bind_mappings = bind_map_key(bind_map, &spec.ident).and_then(|key| bind_map.remove(&key));
+    if let Some(bind_mappings) = bind_mappings {
+        // Turn each BindMapping into a ServiceBind
+
+        // NOTE: We are explicitly NOT generating binds that include
+        // "organization". This is a feature that never quite found
+        // its footing, and will likely be removed / greatly
+        // overhauled Real Soon Now (TM) (as of September 2017).
+        //
+        // As it exists right now, "organization" is a supervisor-wide
+        // setting, and thus is only available for `hab sup run`.
+        // We don't have a way from `hab svc load` to access the organization setting of an
+        // active supervisor, and so we can't generate binds that include organizations.
+        for bind_mapping in bind_mappings.iter() {
+            let group = match spec.service_group_named(&bind_mapping.satisfying_service.name) {
+                Ok(group) => group,
+                Err(err) => {
+                    outputln!(
+                        "Skipping bind {} for {}, {}",
+                        bind_mapping.bind_name,
+                        spec.ident,
+                        err
+                    );
+                    continue;
+                }
+            };
+            let bind = ServiceBind {
+                name: bind_mapping.bind_name.clone(),
+                service_group: group,
+                service_name: Some(bind_mapping.bind_name.clone()),
+                fallback_service_group: None,
+                port: None,
+            };
+            composite_binds.push(bind);
+        }
+    }
+
+    // If anything was overridden or added on the CLI, layer that on
+    // now as well. These will take precedence over anything in the
+    // composite itself.
+    let cli_binds = binds
+        .iter()
+        .filter(|bind| bind.service_name_or("") == spec.ident.name)
+        .cloned()
+        .collect();
+    spec.binds = merge_binds(composite_binds, cli_binds);
+
+    binds
+        .iter()
+        .filter(|bind| !member_names.contains(bind.service_name_or("")))
+        .cloned()
+        .collect()
+}
+
+/// Layers `overrides` on top of `base`, keyed by bind name. When more than one bind has the
+/// same name the last one wins, and every bind in `overrides` comes after those in `base`. The
+/// merged binds are returned sorted by name.
+pub fn merge_binds(base: Vec<ServiceBind>, overrides: Vec<ServiceBind>) -> Vec<ServiceBind> {
+    let mut merged: BTreeMap<String, ServiceBind> = BTreeMap::new();
+    for bind in base.into_iter().chain(overrides) {
+        merged.insert(bind.name.clone(), bind);
+    }
+    merged.into_iter().map(|(_, bind)| bind).collect()
+}
+
+/// Finds the cycles in the graph of service groups formed by the specs' binds, where each spec's
+/// service group depends on the groups its binds target. Services in such a cycle can wait on
+/// each other forever when they use strict binding. Each cycle is returned as the service groups
+/// along it, starting from the group where it was first entered.
+///
+/// Specs whose own service group is invalid, and binds to groups which none of the specs run
+/// in, are ignored.
+pub fn detect_bind_cycles(specs: &[ServiceSpec]) -> Vec<Vec<ServiceGroup>> {
+    let nodes: Vec<(ServiceGroup, Vec<ServiceGroup>)> = specs
+        .iter()
+        .filter_map(|spec| {
+            spec.service_group()
+                .ok()
+                .map(|group| (group, spec.dependency_groups()))
+        })
+        .collect();
+    let edges: Vec<Vec<usize>> = nodes
+        .iter()
+        .map(|&(_, ref deps)| {
+            deps.iter()
+                .filter_map(|dep| nodes.iter().position(|&(ref group, _)| group == dep))
+                .collect()
+        })
+        .collect();
+    let mut state = vec![Visit::Unvisited; nodes.len()];
+    let mut path = Vec::new();
+    let mut cycles = Vec::new();
+    for node in 0..nodes.len() {
+        if state[node] == Visit::Unvisited {
+            visit_bind_graph(node, &edges, &mut state, &mut path, &mut cycles);
+        }
+    }
+    cycles
+        .into_iter()
+        .map(|cycle| cycle.into_iter().map(|n| nodes[n].0.clone()).collect())
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+/// Depth-first walk of the bind graph from `node`. Reaching a node which is still on `path`
+/// closes a cycle, which is recorded as the portion of `path` from that node onwards.
+fn visit_bind_graph(
+    node: usize,
+    edges: &[Vec<usize>],
+    state: &mut [Visit],
+    path: &mut Vec<usize>,
+    cycles: &mut Vec<Vec<usize>>,
+) {
+    state[node] = Visit::InProgress;
+    path.push(node);
+    for &next in edges[node].iter() {
+        match state[next] {
+            Visit::Unvisited => visit_bind_graph(next, edges, state, path, cycles),
+            Visit::InProgress => {
+                let start = path.iter()
+                    .position(|&n| n == next)
+                    .expect("in progress node is on the path");
+                cycles.push(path[start..].to_vec());
+            }
+            Visit::Done => {}
+        }
+    }
+    path.pop();
+    state[node] = Visit::Done;
+}
+
+fn warn_unknown_composite_binds(spec: &ServiceSpec, unknown_binds: &[ServiceBind]) {
+    if unknown_binds.is_empty() {
+        return;
+    }
+    outputln!(
+        "Ignoring bind(s) for services not in the {} composite: {}",
+        spec.composite_name().unwrap_or("unknown"),
+        unknown_binds
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{BufReader, Cursor, Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use std::thread;
+
+    use hcore::error::Error as HError;
+    use hcore::package::PackageIdent;
+    use hcore::service::{ApplicationEnvironment, ServiceGroup};
+    use tempdir::TempDir;
+    use time;
+    use toml;
+
+    use super::*;
+    use error::Error::*;
+
+    fn file_from_str<P: AsRef<Path>>(path: P, content: &str) {
+        fs::create_dir_all(
+            path.as_ref()
+                .parent()
+                .expect("failed to determine file's parent directory"),
+        ).expect("failed to create parent directory recursively");
+        let mut file = File::create(path).expect("failed to create file");
+        file.write_all(content.as_bytes())
+            .expect("failed to write content to file");
+    }
+
+    fn string_from_file<P: AsRef<Path>>(path: P) -> String {
+        let file = File::open(path).expect("failed to open file");
+        let mut file = BufReader::new(file);
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)
+            .expect("cannot read file to string");
+        buf
+    }
+
+    fn test_package_install() -> PackageInstall {
+        PackageInstall::new_from_parts(
+            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+        )
+    }
+
+    fn test_package_install_with_binds(installed_path: &Path, binds: &str) -> PackageInstall {
+        file_from_str(installed_path.join("BINDS"), binds);
+        PackageInstall::new_from_parts(
+            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+            installed_path.to_path_buf(),
+        )
+    }
+
+    #[test]
+    fn service_spec_from_package_adds_placeholder_binds() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let package = test_package_install_with_binds(tmpdir.path(), "database=port host");
+
+        let spec = ServiceSpec::from_package(&package).unwrap();
+
+        assert_eq!(spec.ident, *package.ident());
+        assert_eq!(
+            spec.binds,
+            vec![ServiceBind::from_str("database:database.default").unwrap()]
+        );
+    }
+
+    #[test]
+    fn service_spec_from_str() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            group = "jobs"
+            application_environment = "theinternet.preprod"
+            bldr_url = "http://example.com/depot"
+            topology = "leader"
+            update_strategy = "rolling"
+            binds = ["cache:redis.cache@acmecorp", "db:postgres.app@acmecorp"]
+            config_from = "/only/for/development"
+
+            extra_stuff = "should be ignored"
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(
+            spec.ident,
+            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap()
+        );
+        assert_eq!(spec.group, String::from("jobs"));
+        assert_eq!(
+            spec.application_environment,
+            Some(ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),)
+        );
+        assert_eq!(spec.bldr_url, String::from("http://example.com/depot"));
+        assert_eq!(spec.topology, Topology::Leader);
+        assert_eq!(spec.update_strategy, UpdateStrategy::Rolling);
+        assert_eq!(
+            spec.binds,
+            vec![
+                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
+                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+            ]
+        );
+        assert_eq!(
+            spec.config_from,
+            Some(PathBuf::from("/only/for/development"))
+        );
+    }
+
+    #[test]
+    fn service_spec_from_str_binds_table() {
+        let from_array = ServiceSpec::from_str(
+            r#"
+            ident = "origin/name"
+            binds = ["cache:redis.cache@acmecorp", "db:postgres.app"]
+            "#,
+        ).unwrap();
+        let from_table = ServiceSpec::from_str(
+            r#"
+            ident = "origin/name"
+
+            [binds]
+            db = "postgres.app"
+            cache = "redis.cache@acmecorp"
+            "#,
+        ).unwrap();
+
+        assert_eq!(from_array.binds, from_table.binds);
+        let toml = from_table.to_toml_string().unwrap();
+        assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
+        assert!(!toml.contains("[binds]"));
+    }
+
+    #[test]
+    fn service_spec_from_str_binds_table_invalid_group() {
+        let toml = r#"
+            ident = "origin/name"
+
+            [binds]
+            db = "nosuchservicegroup@nope"
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(e) => match e.err {
+                ServiceSpecParse(_) => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_missing_ident() {
+        let toml = r#""#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(e) => match e.err {
+                MissingRequiredIdent => assert!(true),
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_invalid_topology() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            topology = "smartest-possible"
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(e) => match e.err {
+                ServiceSpecParse(_) => assert!(true),
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_invalid_topology_lists_valid_topologies() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            topology = "smartest-possible"
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(e) => match e.err {
+                ServiceSpecParse(err) => {
+                    let msg = err.to_string();
+                    assert!(msg.contains("smartest-possible"));
+                    assert!(msg.contains("standalone"));
+                    assert!(msg.contains("leader"));
+                }
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn parse_application_environment_missing_environment() {
+        match parse_application_environment("myapp") {
+            Err(e) => match e.err {
+                InvalidApplicationEnvironment(reason) => {
+                    assert!(reason.contains("'myapp'"));
+                    assert!(reason.contains("missing the environment"));
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Application environment should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn parse_application_environment_empty_application() {
+        match parse_application_environment(".prod") {
+            Err(e) => match e.err {
+                InvalidApplicationEnvironment(reason) => {
+                    assert!(reason.contains("application is empty"))
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Application environment should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_invalid_application_environment() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            application_environment = "myapp."
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(e) => match e.err {
+                ServiceSpecParse(err) => assert!(err.to_string().contains("environment is empty")),
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_mixed_case_topology_and_strategy() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            topology = "Leader"
+            update_strategy = "ROLLING"
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.topology, Topology::Leader);
+        assert_eq!(spec.update_strategy, UpdateStrategy::Rolling);
+        let rendered = spec.to_toml_string().unwrap();
+        assert!(rendered.contains("topology = \"leader\""));
+        assert!(rendered.contains("update_strategy = \"rolling\""));
+    }
+
+    #[test]
+    fn service_spec_from_str_invalid_update_strategy_lists_valid_strategies() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            update_strategy = "eventually"
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(e) => match e.err {
+                ServiceSpecParse(err) => {
+                    let msg = err.to_string();
+                    assert!(msg.contains("eventually"));
+                    assert!(msg.contains("at-once"));
+                    assert!(msg.contains("rolling"));
+                }
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_invalid_binds() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            topology = "leader"
+            binds = ["magic:magicness.default", "winning"]
+            "#;
+
+        match ServiceSpec::from_str(toml) {
+            Err(e) => match e.err {
+                ServiceSpecParse(_) => assert!(true),
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_to_toml_string() {
+        let spec = ServiceSpecBuilder::new()
+            .ident(PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap())
+            .group("jobs")
+            .application_environment(
+                ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),
+            )
+            .bldr_url("http://example.com/depot")
+            .channel("unstable")
+            .topology(Topology::Leader)
+            .update_strategy(UpdateStrategy::AtOnce)
+            .binds(vec![
+                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
+                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+            ])
+            .binding_mode(BindingMode::Relaxed)
+            .config_from("/only/for/development")
+            .desired_state(DesiredState::Down)
+            .build()
+            .unwrap();
+        let toml = spec.to_toml_string().unwrap();
+
+        assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
+        assert!(toml.contains(r#"group = "jobs""#));
+        assert!(toml.contains(r#"application_environment = "theinternet.preprod""#,));
+        assert!(toml.contains(r#"bldr_url = "http://example.com/depot""#));
+        assert!(toml.contains(r#"channel = "unstable""#));
+        assert!(toml.contains(r#"topology = "leader""#));
+        assert!(toml.contains(r#"update_strategy = "at-once""#));
+        assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
+        assert!(toml.contains(r#""db:postgres.app@acmecorp""#));
+        assert!(toml.contains(r#"desired_state = "down""#));
+        assert!(toml.contains(r#"config_from = "/only/for/development""#));
+        assert!(toml.contains(r#"binding_mode = "relaxed""#));
+    }
+
+    #[test]
+    fn service_spec_to_redacted_toml_string() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.group = String::from("jobs");
+        spec.binds = vec![ServiceBind::from_str("db:postgres.app").unwrap()];
+        spec.svc_encrypted_password = Some(String::from("sekret"));
+
+        let toml = spec.to_redacted_toml_string().unwrap();
+
+        assert!(!toml.contains("sekret"));
+        assert!(toml.contains(r#"svc_encrypted_password = "<redacted>""#));
+        assert_eq!(spec.svc_encrypted_password, Some(String::from("sekret")));
+        let mut expected = spec.clone();
+        expected.svc_encrypted_password = Some(String::from("<redacted>"));
+        assert_eq!(ServiceSpec::from_str(&toml).unwrap(), expected);
+    }
+
+    #[test]
+    fn service_spec_to_toml_string_orders_binds_by_name() {
+        let spec_with = |binds: &[&str]| {
+            let mut spec =
+                ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+            spec.binds = binds
+                .iter()
+                .map(|bind| ServiceBind::from_str(bind).unwrap())
+                .collect();
+            spec.to_toml_string().unwrap()
+        };
+        let toml = spec_with(&["db:postgres.app", "cache:redis.cache", "auth:keycloak.prod"]);
+
+        assert_eq!(
+            toml,
+            spec_with(&["auth:keycloak.prod", "db:postgres.app", "cache:redis.cache"])
+        );
+        let auth = toml.find("auth:keycloak.prod").unwrap();
+        let cache = toml.find("cache:redis.cache").unwrap();
+        let db = toml.find("db:postgres.app").unwrap();
+        assert!(auth < cache && cache < db);
+    }
+
+    #[test]
+    fn service_spec_sorts_by_ident_then_group() {
+        let spec_for = |ident: &str, group: &str| {
+            let mut spec = ServiceSpec::default_for(PackageIdent::from_str(ident).unwrap());
+            spec.group = String::from(group);
+            spec
+        };
+        let mut specs = vec![
+            spec_for("core/redis/4.0.0/20180101000000", "default"),
+            spec_for("acme/web", "prod"),
+            spec_for("core/redis/3.2.4/20170101000000", "default"),
+            spec_for("acme/web", "default"),
+            spec_for("acme/db/1.0.0/20180101000000", "default"),
+            spec_for("core/redis/3.2.4/20170514150022", "default"),
+        ];
+        specs.sort();
+
+        let order: Vec<String> = specs
+            .iter()
+            .map(|s| format!("{}.{}", s.ident, s.group))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                "acme/db/1.0.0/20180101000000.default",
+                "acme/web.default",
+                "acme/web.prod",
+                "core/redis/3.2.4/20170101000000.default",
+                "core/redis/3.2.4/20170514150022.default",
+                "core/redis/4.0.0/20180101000000.default",
+            ]
+        );
+    }
+
+    #[test]
+    fn service_spec_ordering_agrees_with_eq() {
+        let spec_with = |binds: &[&str]| {
+            let mut spec =
+                ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+            spec.binds = binds
+                .iter()
+                .map(|bind| ServiceBind::from_str(bind).unwrap())
+                .collect();
+            spec
+        };
+        let spec = spec_with(&["db:postgres.app", "cache:redis.cache"]);
+        let mut reloaded = spec.clone();
+        reloaded.record_load(LOADED_FROM_CLI);
+
+        assert_eq!(spec.cmp(&reloaded), cmp::Ordering::Equal);
+        for other in vec![
+            spec_with(&["db:postgres.app", "cache:redis.cache:6380"]),
+            spec_with(&["db:postgres.app|postgres.backup", "cache:redis.cache"]),
+            spec_with(&["db:postgres.app"]),
+        ] {
+            assert_ne!(spec, other);
+            assert_ne!(spec.cmp(&other), cmp::Ordering::Equal);
+        }
+    }
+
+    #[test]
+    fn service_spec_requires_restart_for_ident_bump() {
+        let ident = PackageIdent::from_str("acme/web/1.0.0/20180101000000").unwrap();
+        let running = ServiceSpec::default_for(ident);
+        let mut new = running.clone();
+        new.ident = PackageIdent::from_str("acme/web/1.0.1/20180201000000").unwrap();
+
+        assert!(new.requires_restart_relative_to(&running));
+    }
+
+    #[test]
+    fn service_spec_requires_restart_for_topology_change() {
+        let running = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        let mut new = running.clone();
+        new.topology = Topology::Leader;
+
+        assert!(new.requires_restart_relative_to(&running));
+    }
+
+    #[test]
+    fn service_spec_no_restart_for_binding_mode_change() {
+        let running = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        let mut new = running.clone();
+        new.binding_mode = BindingMode::Relaxed;
+        new.binds = vec![ServiceBind::from_str("db:postgres.default").unwrap()];
+        new.desired_state = DesiredState::Down;
+
+        assert!(!new.requires_restart_relative_to(&running));
+    }
+
+    #[test]
+    fn can_reconfigure_in_place_binds_only() {
+        let from = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        let mut to = from.clone();
+        to.binds = vec![ServiceBind::from_str("db:postgres.default").unwrap()];
+        to.desired_state = DesiredState::Down;
+
+        assert!(can_reconfigure_in_place(&from, &to));
+    }
+
+    #[test]
+    fn can_reconfigure_in_place_ident_change() {
+        let from = ServiceSpec::default_for(
+            PackageIdent::from_str("acme/web/1.0.0/20180101000000").unwrap(),
+        );
+        let mut to = from.clone();
+        to.ident = PackageIdent::from_str("acme/web/1.0.1/20180201000000").unwrap();
+
+        assert!(!can_reconfigure_in_place(&from, &to));
+    }
+
+    #[test]
+    fn can_reconfigure_in_place_group_change() {
+        let from = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        let mut to = from.clone();
+        to.group = String::from("prod");
+
+        assert!(!from.requires_restart_relative_to(&to));
+        assert!(!can_reconfigure_in_place(&from, &to));
+    }
+
+    #[test]
+    fn service_spec_to_minimal_toml_string() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap())
+            .with_topology(Topology::Leader);
+
+        let toml = spec.to_minimal_toml_string().unwrap();
+
+        assert!(toml.contains(r#"ident = "acme/web""#));
+        assert!(toml.contains(r#"topology = "leader""#));
+        assert!(!toml.contains("channel"));
+        assert!(!toml.contains("binds"));
+        assert!(!toml.contains("binding_mode"));
+        assert_eq!(ServiceSpec::from_str(&toml).unwrap(), spec);
+    }
+
+    #[test]
+    fn service_spec_to_minimal_toml_string_keeps_extra_fields() {
+        let spec = ServiceSpec::from_str(
+            r#"
+            ident = "acme/web"
+            channel = "unstable"
+            future_field = "kept"
+            "#,
+        ).unwrap();
+
+        let toml = spec.to_minimal_toml_string().unwrap();
+
+        assert!(toml.contains(r#"channel = "unstable""#));
+        assert!(toml.contains(r#"future_field = "kept""#));
+        assert!(!toml.contains("group"));
+        assert_eq!(ServiceSpec::from_str(&toml).unwrap(), spec);
+    }
+
+    #[test]
+    fn service_spec_content_hash() {
+        let spec = ServiceSpec::from_str(
+            r#"
+            ident = "origin/name"
+            binds = ["db:postgres.app", "cache:redis.cache"]
+            "#,
+        ).unwrap();
+        let same = ServiceSpec::from_str(
+            r#"ident = "origin/name"
+binds = [ "cache:redis.cache",   "db:postgres.app" ]"#,
+        ).unwrap();
+
+        assert_eq!(spec.content_hash().unwrap(), same.content_hash().unwrap());
+
+        let changed = same.with_channel(String::from("unstable"));
+        assert_ne!(spec.content_hash().unwrap(), changed.content_hash().unwrap());
+    }
+
+    #[test]
+    fn service_spec_content_hash_ignores_load_record() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        let mut reloaded = spec.clone();
+        reloaded.record_load(LOADED_FROM_CLI);
+
+        assert_eq!(spec.content_hash().unwrap(), reloaded.content_hash().unwrap());
+    }
+
+    #[test]
+    fn service_spec_display() {
+        let mut spec =
+            ServiceSpec::default_for(PackageIdent::from_str("origin/name/1.2.3").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.cache").unwrap(),
+            ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+        ];
+        let rendered = spec.to_string();
+
+        assert!(rendered.contains("ident: origin/name/1.2.3"));
+        assert!(rendered.contains("\n  cache:redis.cache"));
+        assert!(rendered.contains("\n  db:postgres.app@acmecorp"));
+        assert!(!rendered.contains("config from"));
+
+        spec.config_from = Some(PathBuf::from("/only/for/development"));
+        assert!(spec.to_string()
+            .contains("config from: /only/for/development"));
+    }
+
+    #[test]
+    fn service_spec_to_load_args() {
+        let mut spec =
+            ServiceSpec::default_for(PackageIdent::from_str("origin/name/1.2.3").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.cache").unwrap(),
+            ServiceBind::from_str("db:postgres.app").unwrap(),
+        ];
+        spec.update_strategy = UpdateStrategy::Rolling;
+
+        assert_eq!(
+            spec.to_load_args(),
+            vec![
+                "origin/name/1.2.3",
+                "--strategy",
+                "rolling",
+                "--bind",
+                "cache:redis.cache",
+                "--bind",
+                "db:postgres.app",
+            ]
+        );
+    }
+
+    #[test]
+    fn service_spec_to_load_args_defaults_omitted() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+
+        assert_eq!(spec.to_load_args(), vec!["origin/name"]);
+    }
+
+    #[test]
+    fn service_spec_with_transforms() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.group = String::from("jobs");
+        spec.binds = vec![ServiceBind::from_str("db:postgres.default").unwrap()];
+
+        let transformed = spec.clone()
+            .with_channel(String::from("unstable"))
+            .with_topology(Topology::Leader);
+
+        assert_eq!(&transformed.channel, "unstable");
+        assert_eq!(transformed.topology, Topology::Leader);
+        assert_eq!(transformed.ident, spec.ident);
+        assert_eq!(transformed.group, spec.group);
+        assert_eq!(transformed.binds, spec.binds);
+        assert_eq!(transformed.update_strategy, spec.update_strategy);
+    }
+
+    #[test]
+    fn service_spec_summary_from_spec() {
+        let mut spec =
+            ServiceSpec::default_for(PackageIdent::from_str("origin/name/1.2.3").unwrap());
+        spec.group = String::from("jobs");
+        spec.topology = Topology::Leader;
+        spec.desired_state = DesiredState::Down;
+        spec.channel = String::from("unstable");
+
+        let summary = ServiceSpecSummary::from(&spec);
+
+        assert_eq!(
+            summary,
+            ServiceSpecSummary {
+                ident: PackageIdent::from_str("origin/name/1.2.3").unwrap(),
+                group: String::from("jobs"),
+                topology: Topology::Leader,
+                desired_state: DesiredState::Down,
+            }
+        );
+    }
+
+    #[test]
+    fn service_spec_ref_view() {
+        let mut spec =
+            ServiceSpec::default_for(PackageIdent::from_str("origin/name/1.2.3").unwrap());
+        spec.group = String::from("jobs");
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.cache").unwrap(),
+            ServiceBind::from_str("db:postgres.app").unwrap(),
+        ];
+
+        let view = spec.as_ref_view();
+
+        assert_eq!(view.ident(), &spec.ident);
+        assert_eq!(view.binds(), spec.binds.as_slice());
+        assert_eq!(view.group(), "jobs");
+        assert_eq!(view.topology(), spec.topology);
+        assert_eq!(view.composite(), None);
+    }
+
+    #[test]
+    fn service_spec_sanitize_for_production() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.config_from = Some(PathBuf::from("/only/for/development"));
+        spec.svc_encrypted_password = Some(String::from("sekret"));
+
+        let cleared = spec.sanitize_for_production();
+
+        assert_eq!(
+            cleared,
+            vec![SpecField::ConfigFrom, SpecField::SvcEncryptedPassword]
+        );
+        assert_eq!(spec.config_from, None);
+        assert_eq!(spec.svc_encrypted_password, None);
+        assert!(spec.sanitize_for_production().is_empty());
+    }
+
+    #[test]
+    fn service_spec_service_group() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        spec.group = String::from("prod");
+
+        assert_eq!(
+            spec.service_group().unwrap(),
+            ServiceGroup::from_str("web.prod").unwrap()
+        );
+    }
+
+    #[test]
+    fn service_spec_service_group_with_application_environment() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        spec.group = String::from("prod");
+        spec.application_environment =
+            Some(ApplicationEnvironment::from_str("shop.production").unwrap());
+
+        assert_eq!(
+            spec.service_group().unwrap(),
+            ServiceGroup::from_str("shop.production#web.prod").unwrap()
+        );
+    }
+
+    #[test]
+    fn service_spec_dependency_groups() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("db:postgres.prod").unwrap(),
+            ServiceBind::from_str("cache:redis.prod").unwrap(),
+            ServiceBind::from_str("replica:postgres.prod").unwrap(),
+        ];
+
+        assert_eq!(
+            spec.dependency_groups(),
+            vec![
+                ServiceGroup::from_str("postgres.prod").unwrap(),
+                ServiceGroup::from_str("redis.prod").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn service_spec_service_group_with_group_template() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        spec.group = String::from("jobs_${env}");
+        spec.application_environment =
+            Some(ApplicationEnvironment::from_str("shop.production").unwrap());
+
+        assert_eq!(
+            spec.service_group().unwrap(),
+            ServiceGroup::from_str("shop.production#web.jobs_production").unwrap()
+        );
+        spec.group = String::from("jobs-${app}-${env}");
+        assert_eq!(&spec.resolved_group().unwrap(), "jobs-shop-production");
+    }
+
+    #[test]
+    fn service_spec_service_group_template_without_application_environment() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        spec.group = String::from("jobs_${env}");
+
+        match spec.service_group() {
+            Err(e) => match e.err {
+                UnresolvedGroupTemplate(group) => assert_eq!("jobs_${env}", group),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Group template should not resolve"),
+        }
+    }
+
+    #[test]
+    fn service_spec_composite_member() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.composite = Some(String::from("stack"));
+
+        assert!(spec.is_composite_member());
+        assert_eq!(spec.composite_name(), Some("stack"));
+    }
+
+    #[test]
+    fn service_spec_standalone_is_not_composite_member() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+
+        assert!(!spec.is_composite_member());
+        assert_eq!(spec.composite_name(), None);
+    }
+
+    #[test]
+    fn service_spec_empty_composite_is_not_composite_member() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.composite = Some(String::new());
+
+        assert!(!spec.is_composite_member());
+        assert_eq!(spec.composite_name(), None);
+    }
+
+    #[test]
+    fn service_spec_json_round_trip() {
+        let spec = ServiceSpecBuilder::new()
+            .ident(PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap())
+            .group("jobs")
+            .application_environment(
+                ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),
+            )
+            .topology(Topology::Leader)
+            .update_strategy(UpdateStrategy::Rolling)
+            .binds(vec![
+                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
+                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+            ])
+            .config_from("/only/for/development")
+            .desired_state(DesiredState::Down)
+            .build()
+            .unwrap();
+        let json = spec.to_json_string().unwrap();
+
+        assert!(json.contains(r#""cache:redis.cache@acmecorp""#));
+        assert_eq!(spec, ServiceSpec::from_json_str(&json).unwrap());
+    }
+
+    #[test]
+    fn service_spec_from_json_str_missing_ident() {
+        match ServiceSpec::from_json_str(r#"{"group": "jobs"}"#) {
+            Err(e) => match e.err {
+                MissingRequiredIdent => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec JSON should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_binding_mode_for() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binding_mode = BindingMode::Strict;
+        spec.binding_mode_overrides
+            .insert(String::from("cache"), BindingMode::Relaxed);
+
+        assert_eq!(spec.binding_mode_for("cache"), BindingMode::Relaxed);
+        assert_eq!(spec.binding_mode_for("db"), BindingMode::Strict);
+    }
+
+    #[test]
+    fn service_spec_binding_mode_overrides_round_trip() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.cache").unwrap(),
+            ServiceBind::from_str("db:postgres.app").unwrap(),
+        ];
+        spec.binding_mode = BindingMode::Strict;
+        spec.binding_mode_overrides
+            .insert(String::from("cache"), BindingMode::Relaxed);
+        spec.binding_mode_overrides
+            .insert(String::from("db"), BindingMode::Strict);
+        let toml = spec.to_toml_string().unwrap();
+
+        assert!(toml.contains("[binding_mode_overrides]"));
+        assert!(toml.contains(r#"cache = "relaxed""#));
+        assert!(toml.contains(r#"db = "strict""#));
+        assert_eq!(ServiceSpec::from_str(&toml).unwrap(), spec);
+    }
+
+    #[test]
+    fn service_spec_binding_mode_overrides_default_empty() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            binding_mode = "relaxed"
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert!(spec.binding_mode_overrides.is_empty());
+        assert_eq!(spec.binding_mode_for("anything"), BindingMode::Relaxed);
+        assert!(!spec.to_toml_string()
+            .unwrap()
+            .contains("binding_mode_overrides"));
+    }
+
+    #[test]
+    fn service_spec_remove_bind() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("db:postgres.prod").unwrap(),
+            ServiceBind::from_str("cache:redis.prod").unwrap(),
+        ];
+
+        assert_eq!(
+            spec.remove_bind("db"),
+            Some(ServiceBind::from_str("db:postgres.prod").unwrap())
+        );
+        assert_eq!(
+            spec.binds,
+            vec![ServiceBind::from_str("cache:redis.prod").unwrap()]
+        );
+    }
+
+    #[test]
+    fn service_spec_binds_for_service() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.prod").unwrap(),
+            ServiceBind::from_str("db:postgres.prod").unwrap(),
+            ServiceBind::from_str("sessions:redis.sessions").unwrap(),
+        ];
+
+        let names: Vec<&str> = spec.binds_for_service("redis")
+            .iter()
+            .map(|bind| bind.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["cache", "sessions"]);
+        assert_eq!(spec.binds_for_service("postgres").len(), 1);
+        assert!(spec.binds_for_service("rabbitmq").is_empty());
+    }
+
+    #[test]
+    fn service_spec_unsatisfied_binds() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("db:postgres.prod").unwrap(),
+            ServiceBind::from_str("cache:redis.prod").unwrap(),
+            ServiceBind::from_str("queue:rabbitmq.prod|rabbitmq.default").unwrap(),
+        ];
+        let live_groups: HashSet<ServiceGroup> = vec![
+            ServiceGroup::from_str("postgres.prod").unwrap(),
+            ServiceGroup::from_str("rabbitmq.default").unwrap(),
+        ].into_iter()
+            .collect();
+
+        assert_eq!(
+            spec.unsatisfied_binds(&live_groups),
+            vec![ServiceBind::from_str("cache:redis.prod").unwrap()]
+        );
+    }
+
+    #[test]
+    fn service_spec_remove_missing_bind() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![ServiceBind::from_str("db:postgres.prod").unwrap()];
+
+        assert_eq!(spec.remove_bind("cache"), None);
+        assert_eq!(spec.binds.len(), 1);
+
+        spec.clear_binds();
+        assert!(spec.binds.is_empty());
+    }
+
+    #[test]
+    fn service_spec_builder_defaults() {
+        let spec = ServiceSpecBuilder::new()
+            .ident(PackageIdent::from_str("origin/name").unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(&spec.channel, "stable");
+        assert_eq!(
+            spec,
+            ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap())
+        );
+    }
+
+    #[test]
+    fn service_spec_builder_missing_ident() {
+        match ServiceSpecBuilder::new().group("jobs").build() {
+            Err(e) => match e.err {
+                MissingRequiredIdent => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail to build"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_ident_origin_mismatch() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("other/name").unwrap());
+
+        match spec.validate(&test_package_install()) {
+            Err(e) => match e.err {
+                IdentOriginMismatch { spec, package } => {
+                    assert_eq!(PackageIdent::from_str("other/name").unwrap(), spec);
+                    assert_eq!(
+                        PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+                        package
+                    );
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_duplicate_bind_same_group() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.cache").unwrap(),
+            ServiceBind::from_str("cache:redis.cache").unwrap(),
+        ];
+
+        match spec.validate(&test_package_install()) {
+            Err(e) => match e.err {
+                DuplicateBind(name) => assert_eq!("cache", name),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_duplicate_bind_different_group() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.cache").unwrap(),
+            ServiceBind::from_str("db:postgres.app").unwrap(),
+            ServiceBind::from_str("cache:redis.other").unwrap(),
+        ];
+
+        match spec.validate(&test_package_install()) {
+            Err(e) => match e.err {
+                DuplicateBind(name) => assert_eq!("cache", name),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_config_from_missing() {
+        let tmpdir = TempDir::new("config_from").unwrap();
+        let path = tmpdir.path().join("nope");
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.config_from = Some(path.clone());
+        let opts = ValidateOptions {
+            check_config_from: true,
+            ..Default::default()
+        };
+
+        match spec.validate_with(&test_package_install(), opts) {
+            Err(e) => match e.err {
+                InvalidConfigFrom(p) => assert_eq!(path, p),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_config_from_file() {
+        let tmpdir = TempDir::new("config_from").unwrap();
+        let path = tmpdir.path().join("a_file");
+        file_from_str(&path, "not a directory");
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.config_from = Some(path.clone());
+        let opts = ValidateOptions {
+            check_config_from: true,
+            ..Default::default()
+        };
+
+        match spec.validate_with(&test_package_install(), opts) {
+            Err(e) => match e.err {
+                InvalidConfigFrom(p) => assert_eq!(path, p),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_config_from_directory() {
+        let tmpdir = TempDir::new("config_from").unwrap();
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.config_from = Some(tmpdir.path().to_path_buf());
+        let opts = ValidateOptions {
+            check_config_from: true,
+            ..Default::default()
+        };
+
+        assert!(spec.validate_with(&test_package_install(), opts).is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_reports_missing_and_invalid_binds() {
+        let tmpdir = TempDir::new("pkg").unwrap();
+        let package = test_package_install_with_binds(tmpdir.path(), "database=port host\n");
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![ServiceBind::from_str("cache:redis.default").unwrap()];
+
+        match spec.validate(&package) {
+            Err(e) => match e.err {
+                BindValidation { missing, invalid } => {
+                    assert_eq!(vec![String::from("database")], missing);
+                    assert_eq!(vec![String::from("cache")], invalid);
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_bind_status() {
+        let tmpdir = TempDir::new("pkg").unwrap();
+        let package =
+            test_package_install_with_binds(tmpdir.path(), "database=port host\ncache=port\n");
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("database:postgres.default").unwrap(),
+            ServiceBind::from_str("metrics:statsd.default").unwrap(),
+        ];
+
+        let status = spec.bind_status(&package).unwrap();
+
+        assert_eq!(vec![String::from("database")], status.satisfied);
+        assert_eq!(vec![String::from("cache")], status.missing);
+        assert_eq!(vec![String::from("metrics")], status.extra);
+    }
+
+    #[test]
+    fn service_spec_classify_binds() {
+        let tmpdir = TempDir::new("pkg").unwrap();
+        let package = test_package_install_with_binds(tmpdir.path(), "database=port host");
+        file_from_str(tmpdir.path().join("BINDS_OPTIONAL"), "cache=port");
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.default").unwrap(),
+            ServiceBind::from_str("database:postgres.default").unwrap(),
+            ServiceBind::from_str("metrics:statsd.default").unwrap(),
+        ];
+
+        let kinds = spec.classify_binds(&package).unwrap();
+
+        assert_eq!(kinds.len(), 3);
+        assert_eq!(kinds["database"], BindKind::Required);
+        assert_eq!(kinds["cache"], BindKind::Optional);
+        assert_eq!(kinds["metrics"], BindKind::Unknown);
+    }
+
+    #[test]
+    fn service_spec_validate_structure() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.default").unwrap(),
+            ServiceBind::from_str("db:postgres.default").unwrap(),
+        ];
+
+        assert!(spec.validate_structure().is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_structure_duplicate_bind() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("db:postgres.default").unwrap(),
+            ServiceBind::from_str("db:mysql.default").unwrap(),
+        ];
+
+        match spec.validate_structure() {
+            Err(e) => match e.err {
+                DuplicateBind(name) => assert_eq!("db", name),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_self_bind() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.group = String::from("prod");
+        spec.binds = vec![ServiceBind::from_str("peer:name.prod").unwrap()];
+
+        match spec.validate_structure() {
+            Err(e) => match e.err {
+                SelfBind(name) => assert_eq!("peer", name),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_empty_group() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.group = String::from("  ");
+
+        match spec.validate(&test_package_install()) {
+            Err(e) => match e.err {
+                InvalidGroup(group) => assert_eq!("  ", group),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_group_with_separator() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.group = String::from("prod.east");
+
+        match spec.validate_structure() {
+            Err(e) => match e.err {
+                InvalidGroup(group) => assert_eq!("prod.east", group),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_group() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.group = String::from("prod-east_2");
+
+        assert!(spec.validate(&test_package_install()).is_ok());
+        assert!(spec.validate_structure().is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_channel() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.channel = String::from("unstable_v2-rc");
+
+        assert!(spec.validate(&test_package_install()).is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_channel_with_space() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.channel = String::from("my channel");
+
+        match spec.validate(&test_package_install()) {
+            Err(e) => match e.err {
+                InvalidChannel(channel) => assert_eq!("my channel", channel),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_empty_channel() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.channel = String::new();
+
+        assert!(spec.validate_channel().is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_sibling_bind() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.group = String::from("prod");
+        spec.binds = vec![
+            ServiceBind::from_str("peer:name.staging").unwrap(),
+            ServiceBind::from_str("db:postgres.prod").unwrap(),
+        ];
+
+        assert!(spec.validate_structure().is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_composite_member_org_bind() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.composite = Some(String::from("builder"));
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.default").unwrap(),
+            ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+        ];
+
+        match spec.validate_structure() {
+            Err(e) => match e.err {
+                UnsupportedOrgBind(binds) => {
+                    assert_eq!(vec![String::from("db:postgres.app@acmecorp")], binds)
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+
+        spec.composite = None;
+        assert!(spec.validate_structure().is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_structure_missing_ident() {
+        match ServiceSpec::default().validate_structure() {
+            Err(e) => match e.err {
+                MissingRequiredIdent => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_leader_at_once_strict() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.topology = Topology::Leader;
+        spec.update_strategy = UpdateStrategy::AtOnce;
+        let opts = ValidateOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        match spec.validate_with(&test_package_install(), opts) {
+            Err(e) => match e.err {
+                IncompatibleTopologyStrategy { topology, strategy } => {
+                    assert_eq!(topology, Topology::Leader);
+                    assert_eq!(strategy, UpdateStrategy::AtOnce);
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_leader_at_once_warns_by_default() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.topology = Topology::Leader;
+        spec.update_strategy = UpdateStrategy::AtOnce;
+
+        assert!(spec.validate(&test_package_install()).is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_config_from_with_channel_strict() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.config_from = Some(PathBuf::from("/only/for/development"));
+        spec.channel = String::from("unstable");
+        let opts = ValidateOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        match spec.validate_with(&test_package_install(), opts) {
+            Err(e) => match e.err {
+                ConflictingConfigSource(path, channel) => {
+                    assert_eq!(path, PathBuf::from("/only/for/development"));
+                    assert_eq!(channel, "unstable");
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+        assert!(spec.validate(&test_package_install()).is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_config_from_or_channel_alone_strict() {
+        let opts = ValidateOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let mut config_from =
+            ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        config_from.config_from = Some(PathBuf::from("/only/for/development"));
+        let channel = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap())
+            .with_channel(String::from("unstable"));
+
+        assert!(config_from.validate_with(&test_package_install(), opts).is_ok());
+        assert!(channel.validate_with(&test_package_install(), opts).is_ok());
+    }
+
+    #[test]
+    fn service_spec_validate_benign_topology_strategies_strict() {
+        let opts = ValidateOptions {
+            strict: true,
+            ..Default::default()
+        };
+        for &(topology, strategy) in &[
+            (Topology::Leader, UpdateStrategy::Rolling),
+            (Topology::Leader, UpdateStrategy::None),
+            (Topology::Standalone, UpdateStrategy::AtOnce),
+        ] {
+            let mut spec =
+                ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+            spec.topology = topology;
+            spec.update_strategy = strategy;
+
+            assert!(spec.validate_with(&test_package_install(), opts).is_ok());
+        }
+    }
+
+    #[test]
+    fn service_spec_validate_config_from_unchecked_by_default() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.config_from = Some(PathBuf::from("/nope/not/here"));
+
+        assert!(spec.validate(&test_package_install()).is_ok());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn service_spec_validate_malformed_encrypted_password() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.svc_encrypted_password = Some(String::from("not a password!"));
+
+        match spec.validate(&test_package_install()) {
+            Err(e) => match e.err {
+                InvalidEncryptedPassword => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn service_spec_validate_encrypted_password() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.svc_encrypted_password =
+            Some(String::from("AQAAANCMnd8BFdERjHoAwE/Cl+sBAAAAsz3Rf4aJ5kG0eH7e2q1hKA=="));
+
+        assert!(spec.validate(&test_package_install()).is_ok());
+    }
+
+    #[test]
+    fn service_spec_to_toml_string_invalid_ident() {
+        // Remember: the default implementation of `PackageIdent` is an invalid identifier, missing
+        // origin and name--we're going to exploit this here
+        let spec = ServiceSpec::default();
+
+        match spec.to_toml_string() {
+            Err(e) => match e.err {
+                MissingRequiredIdent => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec TOML should fail to render"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_file() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            group = "jobs"
+            application_environment = "theinternet.preprod"
+            bldr_url = "http://example.com/depot"
+            topology = "leader"
+            update_strategy = "rolling"
+            binds = ["cache:redis.cache@acmecorp", "db:postgres.app@acmecorp"]
+            config_from = "/only/for/development"
+
+            extra_stuff = "should be ignored"
+            "#;
+        file_from_str(&path, toml);
+        let spec = ServiceSpec::from_file(path).unwrap();
+
+        assert_eq!(
+            spec.ident,
+            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap()
+        );
+        assert_eq!(spec.group, String::from("jobs"));
+        assert_eq!(spec.bldr_url, String::from("http://example.com/depot"));
+        assert_eq!(spec.topology, Topology::Leader);
+        assert_eq!(
+            spec.application_environment,
+            Some(ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),)
+        );
+        assert_eq!(spec.update_strategy, UpdateStrategy::Rolling);
+        assert_eq!(
+            spec.binds,
+            vec![
+                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
+                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+            ]
+        );
+        assert_eq!(&spec.channel, "stable");
+        assert_eq!(
+            spec.config_from,
+            Some(PathBuf::from("/only/for/development"))
+        );
+
+        assert_eq!(
+            spec.binding_mode,
+            BindingMode::Strict,
+            "Strict is the default mode, if nothing was previously specified."
+        );
+    }
+
+    #[test]
+    fn service_spec_paused_desired_state_round_trip() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.desired_state = DesiredState::Paused;
+        let toml = spec.to_toml_string().unwrap();
+
+        assert!(toml.contains(r#"desired_state = "paused""#));
+        let reloaded = ServiceSpec::from_str(&toml).unwrap();
+        assert_eq!(reloaded.desired_state, DesiredState::Paused);
+        assert_ne!(reloaded.desired_state, DesiredState::Up);
+        assert_ne!(reloaded.desired_state, DesiredState::Down);
+    }
+
+    #[test]
+    fn desired_state_from_str() {
+        assert_eq!(DesiredState::from_str("up").unwrap(), DesiredState::Up);
+        assert_eq!(DesiredState::from_str("down").unwrap(), DesiredState::Down);
+        assert_eq!(
+            DesiredState::from_str("Paused").unwrap(),
+            DesiredState::Paused
+        );
+        assert!(DesiredState::from_str("sleeping").is_err());
+    }
+
+    #[test]
+    fn service_spec_from_str_strict_unknown_field() {
+        let toml = r#"
+            ident = "origin/name"
+            topolgy = "leader"
+            "#;
+
+        assert!(ServiceSpec::from_str(toml).is_ok());
+        match ServiceSpec::from_str_strict(toml) {
+            Err(e) => match e.err {
+                UnknownSpecField(field) => assert_eq!("topolgy", field),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_strict() {
+        let toml = r#"
+            ident = "origin/name"
+            topology = "leader"
+            "#;
+
+        assert_eq!(
+            ServiceSpec::from_str_strict(toml).unwrap().topology,
+            Topology::Leader
+        );
+    }
+
+    #[test]
+    fn service_spec_from_str_strict_versioned() {
+        let toml = r#"
+            format_version = 1
+            ident = "origin/name"
+            "#;
+
+        assert_eq!(
+            ServiceSpec::from_str_strict(toml).unwrap().ident,
+            PackageIdent::from_str("origin/name").unwrap()
+        );
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        let rendered = spec.to_toml_string().unwrap();
+        assert!(rendered.starts_with(&format!("format_version = {}\n", SPEC_FORMAT_VERSION)));
+        assert_eq!(ServiceSpec::from_str_strict(&rendered).unwrap(), spec);
+    }
+
+    #[test]
+    fn service_spec_from_str_with_env_defined_var() {
+        env::set_var("HAB_SPEC_TEST_DEFINED_CHANNEL", "unstable");
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            channel = "${HAB_SPEC_TEST_DEFINED_CHANNEL}"
+            bldr_url = "https://${HAB_SPEC_TEST_DEFINED_CHANNEL}.example.com"
+            "#;
+        let spec = ServiceSpec::from_str_with_env(toml).unwrap();
+
+        assert_eq!(&spec.channel, "unstable");
+        assert_eq!(&spec.bldr_url, "https://unstable.example.com");
+        assert_eq!(&spec.group, "default");
+    }
+
+    #[test]
+    fn service_spec_from_str_with_env_undefined_var() {
+        env::remove_var("HAB_SPEC_TEST_UNDEFINED_GROUP");
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            group = "${HAB_SPEC_TEST_UNDEFINED_GROUP}"
+            "#;
+
+        match ServiceSpec::from_str_with_env(toml) {
+            Err(e) => match e.err {
+                UndefinedSpecVar(var) => assert_eq!("HAB_SPEC_TEST_UNDEFINED_GROUP", var),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec TOML should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_str_with_env_escaped_dollar() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            group = "$${NOT_A_VAR}"
+            channel = "cost$$"
+            "#;
+        let spec = ServiceSpec::from_str_with_env(toml).unwrap();
+
+        assert_eq!(&spec.group, "${NOT_A_VAR}");
+        assert_eq!(&spec.channel, "cost$");
+    }
+
+    #[test]
+    fn service_spec_from_reader() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            group = "jobs"
+            topology = "leader"
+            binds = ["cache:redis.cache@acmecorp"]
+            "#;
+        let spec = ServiceSpec::from_reader(Cursor::new(toml.as_bytes())).unwrap();
+
+        assert_eq!(
+            spec.ident,
+            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap()
+        );
+        assert_eq!(spec.group, String::from("jobs"));
+        assert_eq!(spec.topology, Topology::Leader);
+        assert_eq!(
+            spec.binds,
+            vec![ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap()]
+        );
+    }
+
+    fn save_load_all_fixture(specs_path: &Path) {
+        for name in vec!["web", "db"] {
+            let ident = PackageIdent::from_str(&format!("acme/{}", name)).unwrap();
+            let mut spec = ServiceSpec::default_for(ident);
+            spec.composite = Some(String::from("stack"));
+            spec.to_file(specs_path.join(spec.file_name())).unwrap();
+        }
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("acme/cron").unwrap());
+        spec.to_file(specs_path.join(spec.file_name())).unwrap();
+    }
+
+    #[test]
+    fn load_grouped_separates_composite_members() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        save_load_all_fixture(tmpdir.path());
+
+        let (standalone, members) = load_grouped(tmpdir.path()).unwrap();
+
+        assert_eq!(
+            standalone.iter().map(|s| &s.ident).collect::<Vec<_>>(),
+            vec![&PackageIdent::from_str("acme/cron").unwrap()]
+        );
+        assert_eq!(members.len(), 1);
+        assert_eq!(
+            members["stack"].iter().map(|s| &s.ident).collect::<Vec<_>>(),
+            vec![
+                &PackageIdent::from_str("acme/db").unwrap(),
+                &PackageIdent::from_str("acme/web").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn spec_load_all_composite_missing_member() {
+        let tmpdir = TempDir::new("sup").unwrap();
+        let specs_path = tmpdir.path().join("specs");
+        let composites_path = tmpdir.path().join("composites");
+        save_load_all_fixture(&specs_path);
+        file_from_str(
+            composites_path.join("stack.spec"),
+            r#"
+            ident = "acme/stack"
+            package_ident = "acme/stack/1.0.0/20180101000000"
+            services = ["acme/web/1.0.0/20180101000000", "acme/db/1.0.0/20180101000000",
+                        "acme/queue/1.0.0/20180101000000"]
+            "#,
+        );
+
+        match Spec::load_all(&specs_path, &composites_path) {
+            Err(e) => match e.err {
+                MissingCompositeMember { composite, member } => {
+                    assert_eq!("stack", composite);
+                    assert_eq!(
+                        PackageIdent::from_str("acme/queue/1.0.0/20180101000000").unwrap(),
+                        member
+                    );
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Composite with a missing member should fail to load"),
+        }
+    }
+
+    #[test]
+    fn spec_load_all_composite_group_mismatch() {
+        let tmpdir = TempDir::new("sup").unwrap();
+        let specs_path = tmpdir.path().join("specs");
+        let composites_path = tmpdir.path().join("composites");
+        save_load_all_fixture(&specs_path);
+        let mut db = ServiceSpec::from_file(specs_path.join("db.spec")).unwrap();
+        db.group = String::from("staging");
+        db.to_file(specs_path.join("db.spec")).unwrap();
+        file_from_str(
+            composites_path.join("stack.spec"),
+            r#"
+            ident = "acme/stack"
+            package_ident = "acme/stack/1.0.0/20180101000000"
+            "#,
+        );
+
+        match Spec::load_all(&specs_path, &composites_path) {
+            Err(e) => match e.err {
+                CompositeGroupMismatch(composite, members) => {
+                    assert_eq!(PackageIdent::from_str("acme/stack").unwrap(), composite);
+                    assert_eq!(vec![String::from("acme/web")], members);
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Composite members should fail validation"),
+        }
+    }
+
+    fn composite_bind_fixture() -> (CompositeSpec, BindMap) {
+        let composite = CompositeSpec::from_str(
+            r#"
+            ident = "acme/stack"
+            package_ident = "acme/stack/1.0.0/20180101000000"
+            "#,
+        ).unwrap();
+        let mut bind_map = BindMap::new();
+        bind_map.insert(
+            PackageIdent::from_str("acme/web/1.0.0/20180101000000").unwrap(),
+            vec![BindMapping {
+                bind_name: String::from("database"),
+                satisfying_service: PackageIdent::from_str("acme/db/1.0.0/20180101000000")
+                    .unwrap(),
+            }],
+        );
+        (composite, bind_map)
+    }
+
+    #[test]
+    fn composite_spec_validate_binds_between_members() {
+        let (composite, bind_map) = composite_bind_fixture();
+        let mut web = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        web.binds = vec![
+            ServiceBind::from_str("database:db.default").unwrap(),
+            // Added on the CLI, so it may bind outside the composite
+            ServiceBind::from_str("cache:redis.default").unwrap(),
+        ];
+        let db = ServiceSpec::default_for(PackageIdent::from_str("acme/db").unwrap());
+
+        composite.validate_binds(&[web, db], &bind_map).unwrap();
+    }
+
+    #[test]
+    fn composite_spec_validate_binds_non_member() {
+        let (composite, bind_map) = composite_bind_fixture();
+        let mut web = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        web.binds = vec![ServiceBind::from_str("database:db.default").unwrap()];
+        let cron = ServiceSpec::default_for(PackageIdent::from_str("acme/cron").unwrap());
+
+        match composite.validate_binds(&[web, cron], &bind_map) {
+            Err(e) => match e.err {
+                UnsatisfiableCompositeBind {
+                    composite,
+                    member,
+                    bind,
+                    satisfying_service,
+                } => {
+                    assert_eq!("stack", composite);
+                    assert_eq!(PackageIdent::from_str("acme/web").unwrap(), member);
+                    assert_eq!("database", bind);
+                    assert_eq!(
+                        PackageIdent::from_str("acme/db/1.0.0/20180101000000").unwrap(),
+                        satisfying_service
+                    );
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Bind to a non-member should fail validation"),
+        }
+    }
+
+    #[test]
+    fn composite_spec_validate_binds_ignores_overridden_bind() {
+        let (composite, bind_map) = composite_bind_fixture();
+        let mut web = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        web.binds = vec![ServiceBind::from_str("database:postgres.prod").unwrap()];
+
+        composite.validate_binds(&[web], &bind_map).unwrap();
+    }
+
+    #[test]
+    fn composite_spec_start_order_chain() {
+        let (composite, _) = composite_bind_fixture();
+        let mut web = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        web.binds = vec![ServiceBind::from_str("backend:api.default").unwrap()];
+        let mut api = ServiceSpec::default_for(PackageIdent::from_str("acme/api").unwrap());
+        api.binds = vec![ServiceBind::from_str("database:db.default").unwrap()];
+        let db = ServiceSpec::default_for(PackageIdent::from_str("acme/db").unwrap());
+
+        assert_eq!(
+            composite.start_order(&[web, api, db]).unwrap(),
+            vec![
+                PackageIdent::from_str("acme/db").unwrap(),
+                PackageIdent::from_str("acme/api").unwrap(),
+                PackageIdent::from_str("acme/web").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn composite_spec_start_order_cycle() {
+        let (composite, _) = composite_bind_fixture();
+        let mut web = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        web.binds = vec![ServiceBind::from_str("backend:api.default").unwrap()];
+        let mut api = ServiceSpec::default_for(PackageIdent::from_str("acme/api").unwrap());
+        api.binds = vec![ServiceBind::from_str("frontend:web.default").unwrap()];
+        let cron = ServiceSpec::default_for(PackageIdent::from_str("acme/cron").unwrap());
+
+        match composite.start_order(&[web, api, cron]) {
+            Err(e) => match e.err {
+                CompositeCycle(composite, members) => {
+                    assert_eq!(PackageIdent::from_str("acme/stack").unwrap(), composite);
+                    assert_eq!(
+                        vec![String::from("acme/web"), String::from("acme/api")],
+                        members
+                    );
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Members binding in a cycle should have no start order"),
+        }
+    }
+
+    #[test]
+    fn spec_member_idents_for_composite() {
+        let composite = CompositeSpec::from_str(
+            r#"
+            ident = "acme/stack"
+            package_ident = "acme/stack/1.0.0/20180101000000"
+            "#,
+        ).unwrap();
+        let members = vec![
+            ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap()),
+            ServiceSpec::default_for(PackageIdent::from_str("acme/db").unwrap()),
+        ];
+        let spec = Spec::Composite(composite, members);
+
+        assert_eq!(
+            spec.member_idents(),
+            vec![
+                &PackageIdent::from_str("acme/web").unwrap(),
+                &PackageIdent::from_str("acme/db").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn spec_member_idents_for_service() {
+        let ident = PackageIdent::from_str("acme/cron").unwrap();
+        let spec = Spec::Service(ServiceSpec::default_for(ident.clone()));
+
+        assert_eq!(spec.member_idents(), vec![&ident]);
+    }
+
+    #[test]
+    fn spec_load_all_groups_composite_members() {
+        let tmpdir = TempDir::new("sup").unwrap();
+        let specs_path = tmpdir.path().join("specs");
+        let composites_path = tmpdir.path().join("composites");
+        save_load_all_fixture(&specs_path);
+        file_from_str(
+            composites_path.join("stack.spec"),
+            r#"
+            ident = "acme/stack"
+            package_ident = "acme/stack/1.0.0/20180101000000"
+            "#,
+        );
+
+        let specs = Spec::load_all(&specs_path, &composites_path).unwrap();
+
+        assert_eq!(specs.len(), 2);
+        match specs[0] {
+            Spec::Service(ref spec) => {
+                assert_eq!(spec.ident, PackageIdent::from_str("acme/cron").unwrap())
+            }
+            _ => panic!("Expected a standalone service spec"),
+        }
+        match specs[1] {
+            Spec::Composite(ref composite, ref members) => {
+                assert_eq!(
+                    composite.ident(),
+                    &PackageIdent::from_str("acme/stack").unwrap()
+                );
+                let names: Vec<&str> = members.iter().map(|s| s.ident.name.as_str()).collect();
+                assert_eq!(names, vec!["db", "web"]);
+            }
+            _ => panic!("Expected a composite spec"),
+        }
+    }
+
+    #[test]
+    fn spec_load_all_missing_composite_loads_members_standalone() {
+        let tmpdir = TempDir::new("sup").unwrap();
+        let specs_path = tmpdir.path().join("specs");
+        let composites_path = tmpdir.path().join("composites");
+        save_load_all_fixture(&specs_path);
+
+        let specs = Spec::load_all(&specs_path, &composites_path).unwrap();
+
+        let names: Vec<&str> = specs
+            .iter()
+            .map(|s| match *s {
+                Spec::Service(ref spec) => spec.ident.name.as_str(),
+                Spec::Composite(..) => panic!("Expected only standalone service specs"),
+            })
+            .collect();
+        assert_eq!(names, vec!["cron", "db", "web"]);
+    }
+
+    #[test]
+    fn load_spec_by_name_present() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        save_load_all_fixture(tmpdir.path());
+
+        let spec = load_spec_by_name(tmpdir.path(), "cron").unwrap().unwrap();
+
+        assert_eq!(spec.ident, PackageIdent::from_str("acme/cron").unwrap());
+    }
+
+    #[test]
+    fn load_spec_by_name_absent() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        save_load_all_fixture(tmpdir.path());
+
+        assert_eq!(load_spec_by_name(tmpdir.path(), "queue").unwrap(), None);
+    }
+
+    #[test]
+    fn spec_iter_stops_at_first_match() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        for ident in &["acme/api", "acme/db"] {
+            let spec = ServiceSpec::default_for(PackageIdent::from_str(ident).unwrap());
+            spec.to_file(spec.path_in(tmpdir.path())).unwrap();
+        }
+        file_from_str(tmpdir.path().join("web.spec"), "not = [valid toml");
+
+        let mut specs = spec_iter(tmpdir.path()).unwrap();
+        let found = specs
+            .find(|spec| match *spec {
+                Ok(ref spec) => spec.ident.name == "db",
+                Err(_) => false,
+            })
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(found.ident, PackageIdent::from_str("acme/db").unwrap());
+        // The broken spec after the match is only parsed on request, and fails on its own.
+        match specs.next() {
+            Some(Err(e)) => match e.err {
+                ServiceSpecParse(_) => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            _ => panic!("Expected the broken spec to fail to parse"),
+        }
+        assert!(specs.next().is_none());
+    }
+
+    fn desired_state_fixture() -> Vec<ServiceSpec> {
+        vec![
+            ("acme/web", DesiredState::Up),
+            ("acme/db", DesiredState::Down),
+            ("acme/cron", DesiredState::Paused),
+            ("acme/api", DesiredState::Up),
+        ].into_iter()
+            .map(|(ident, state)| {
+                let mut spec = ServiceSpec::default_for(PackageIdent::from_str(ident).unwrap());
+                spec.desired_state = state;
+                spec
+            })
+            .collect()
+    }
+
+    #[test]
+    fn specs_to_start_filters_up_specs() {
+        let specs = desired_state_fixture();
+
+        let names: Vec<&str> = specs_to_start(&specs)
+            .iter()
+            .map(|s| s.ident.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["web", "api"]);
+    }
+
+    #[test]
+    fn specs_to_stop_filters_down_and_paused_specs() {
+        let specs = desired_state_fixture();
+
+        let names: Vec<&str> = specs_to_stop(&specs)
+            .iter()
+            .map(|s| s.ident.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["db", "cron"]);
+    }
+
+    #[test]
+    fn specs_in_group_filters_by_service_group() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        for &(ident, group) in &[
+            ("acme/web", "prod"),
+            ("acme/web2", "prod"),
+            ("acme/db", "staging"),
+        ] {
+            let mut spec = ServiceSpec::default_for(PackageIdent::from_str(ident).unwrap());
+            spec.group = String::from(group);
+            spec.to_file(tmpdir.path().join(spec.file_name())).unwrap();
+        }
+
+        let specs = specs_in_group(
+            tmpdir.path(),
+            &ServiceGroup::from_str("db.staging").unwrap(),
+        ).unwrap();
+        let idents: Vec<String> = specs.iter().map(|s| s.ident.to_string()).collect();
+        assert_eq!(idents, vec!["acme/db"]);
+        assert_eq!(&specs[0].group, "staging");
+
+        let specs =
+            specs_in_group(tmpdir.path(), &ServiceGroup::from_str("web.prod").unwrap()).unwrap();
+        let idents: Vec<String> = specs.iter().map(|s| s.ident.to_string()).collect();
+        assert_eq!(idents, vec!["acme/web"]);
+    }
+
+    #[test]
+    fn migrate_spec_dir_rewrites_only_legacy_specs() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let legacy_path = tmpdir.path().join("legacy.spec");
+        file_from_str(
+            &legacy_path,
+            r#"
+            ident = "origin/legacy"
+            group = ""
+            topology = "standalone"
+            "#,
+        );
+        let current = ServiceSpec::default_for(PackageIdent::from_str("origin/current").unwrap());
+        let current_path = tmpdir.path().join(current.file_name());
+        current.to_file(&current_path).unwrap();
+        let current_modified = fs::metadata(&current_path).unwrap().modified().unwrap();
+
+        let migrated = migrate_spec_dir(tmpdir.path()).unwrap();
+
+        assert_eq!(migrated, vec![legacy_path.clone()]);
+        let legacy = ServiceSpec::from_file(&legacy_path).unwrap();
+        let mut content = String::new();
+        File::open(&legacy_path)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, legacy.to_toml_string().unwrap());
+        assert_eq!(
+            current_modified,
+            fs::metadata(&current_path).unwrap().modified().unwrap()
+        );
+        assert_eq!(legacy.group, "default");
+        assert_eq!(legacy.loaded_from, Some(LOADED_FROM_MIGRATION.to_string()));
+        assert!(legacy.last_loaded_at.is_some());
+        assert!(migrate_spec_dir(tmpdir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn spec_dir_rewrites_leave_current_hand_written_specs_untouched() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let hand_written = r#"
+            # Our web frontend
+            ident   = "origin/web"
+            binds = [ "cache:redis.default",   "db:postgres.default" ]
+            "#;
+        let hand_written_path = tmpdir.path().join("web.spec");
+        file_from_str(&hand_written_path, hand_written);
+        let with_header = ServiceSpec::default_for(PackageIdent::from_str("origin/db").unwrap());
+        let with_header_path = with_header.path_in(tmpdir.path());
+        with_header.to_file_with_header(&with_header_path).unwrap();
+        let read = |path: &Path| {
+            let mut content = String::new();
+            File::open(path)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        let header_content = read(&with_header_path);
+
+        assert!(migrate_spec_dir(tmpdir.path()).unwrap().is_empty());
+        assert!(normalize_spec_dir(tmpdir.path()).unwrap().is_empty());
+        assert_eq!(read(&hand_written_path), hand_written);
+        assert_eq!(read(&with_header_path), header_content);
+    }
+
+    #[test]
+    fn service_spec_record_load() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.record_load(LOADED_FROM_CLI);
+
+        assert_eq!(spec.loaded_from, Some(LOADED_FROM_CLI.to_string()));
+        let loaded_at = spec.last_loaded_at.clone().unwrap();
+        assert!(time::strptime(&loaded_at, "%Y-%m-%dT%H:%M:%SZ").is_ok());
+
+        let toml = spec.to_toml_string().unwrap();
+        let reloaded = ServiceSpec::from_str(&toml).unwrap();
+        assert_eq!(reloaded.last_loaded_at, Some(loaded_at));
+        assert_eq!(reloaded.loaded_from, Some(LOADED_FROM_CLI.to_string()));
+    }
+
+    #[test]
+    fn service_spec_load_record_ignored_by_equality() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        let mut reloaded = spec.clone();
+        reloaded.record_load(LOADED_FROM_CLI);
+
+        assert_eq!(spec, reloaded);
+        assert_eq!(spec.cmp(&reloaded), cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn service_spec_into_spec_leaves_load_record_to_caller() {
+        let mut msg = protocol::ctl::SvcLoad::default();
+        msg.ident = Some(PackageIdent::from_str("origin/stack").unwrap().into());
+        let mut spec = ServiceSpec::default();
+        msg.into_spec(&mut spec);
+        let services = vec![
+            PackageIdent::from_str("origin/web").unwrap(),
+            PackageIdent::from_str("origin/db").unwrap(),
+        ];
+        let members = msg.into_composite_spec("stack".to_string(), services, BindMap::new(), None);
+
+        for spec in members.iter().chain(Some(&spec)) {
+            assert_eq!(spec.loaded_from, None);
+            assert_eq!(spec.last_loaded_at, None);
+        }
+    }
+
+    #[test]
+    fn service_spec_into_composite_spec_strategy_overrides() {
+        let mut msg = protocol::ctl::SvcLoad::default();
+        msg.ident = Some(PackageIdent::from_str("origin/stack").unwrap().into());
+        msg.update_strategy = Some(UpdateStrategy::Rolling as i32);
+        let services = vec![
+            PackageIdent::from_str("origin/web").unwrap(),
+            PackageIdent::from_str("origin/db").unwrap(),
+            PackageIdent::from_str("origin/cache").unwrap(),
+        ];
+        let mut strategies = HashMap::new();
+        strategies.insert(String::from("db"), UpdateStrategy::AtOnce);
+
+        let specs = msg.into_composite_spec(
+            "stack".to_string(),
+            services,
+            BindMap::new(),
+            Some(&strategies),
+        );
+
+        let strategies: Vec<(&str, UpdateStrategy)> = specs
+            .iter()
+            .map(|s| (s.ident.name.as_str(), s.update_strategy))
+            .collect();
+        assert_eq!(
+            strategies,
+            vec![
+                ("web", UpdateStrategy::Rolling),
+                ("db", UpdateStrategy::AtOnce),
+                ("cache", UpdateStrategy::Rolling),
+            ]
+        );
+    }
+
+    #[test]
+    fn expand_composite_matches_into_composite_spec() {
+        let mut msg = protocol::ctl::SvcLoad::default();
+        msg.ident = Some(PackageIdent::from_str("origin/stack").unwrap().into());
+        msg.group = Some(String::from("prod"));
+        msg.bldr_channel = Some(String::from("unstable"));
+        msg.binds = Some(protocol::ctl::ServiceBindList::default());
+        let services = vec![
+            PackageIdent::from_str("origin/web").unwrap(),
+            PackageIdent::from_str("origin/db").unwrap(),
+        ];
+        let bind_map = || {
+            let mut bind_map = BindMap::new();
+            bind_map.insert(
+                PackageIdent::from_str("origin/web").unwrap(),
+                vec![BindMapping {
+                    bind_name: String::from("database"),
+                    satisfying_service: PackageIdent::from_str("origin/db").unwrap(),
+                }],
+            );
+            bind_map
+        };
+        let mut base = ServiceSpec::default();
+        msg.into_spec(&mut base);
+
+        let expected =
+            msg.into_composite_spec("stack".to_string(), services.clone(), bind_map(), None);
+        let expanded = expand_composite(&base, "stack", services.clone(), bind_map(), Some(&[]));
+
+        assert_eq!(expanded, expected);
+        assert_eq!(expanded[0].binds.len(), 1);
+        assert_eq!(
+            expanded[0].binds[0].service_group,
+            ServiceGroup::from_str("db.prod").unwrap()
+        );
+
+        msg.binds = None;
+        let expected =
+            msg.into_composite_spec("stack".to_string(), services.clone(), bind_map(), None);
+        let expanded = expand_composite(&base, "stack", services, bind_map(), None);
+
+        assert_eq!(expanded, expected);
+        assert!(expanded.iter().all(|spec| spec.binds.is_empty()));
+    }
+
+    #[test]
+    fn bind_mappings_for_falls_back_to_origin_and_name() {
+        let mut bind_map = BindMap::new();
+        bind_map.insert(
+            PackageIdent::from_str("acme/web").unwrap(),
+            vec![BindMapping {
+                bind_name: String::from("database"),
+                satisfying_service: PackageIdent::from_str("acme/db").unwrap(),
+            }],
+        );
+        let ident = PackageIdent::from_str("acme/web/1.0.0/20180101000000").unwrap();
+
+        let mappings = bind_mappings_for(&bind_map, &ident).unwrap();
+        assert_eq!(mappings[0].bind_name, "database");
+        assert!(
+            bind_mappings_for(&bind_map, &PackageIdent::from_str("acme/api").unwrap()).is_none()
+        );
+
+        let base = ServiceSpec::default();
+        let expanded = expand_composite(&base, "stack", vec![ident], bind_map, Some(&[]));
+        assert_eq!(
+            expanded[0].binds[0].service_group,
+            ServiceGroup::from_str("db.default").unwrap()
+        );
+    }
+
+    #[test]
+    fn expand_composite_member_overrides() {
+        let base = ServiceSpec::default().with_channel(String::from("unstable"));
+        let services = vec![
+            PackageIdent::from_str("acme/web").unwrap(),
+            PackageIdent::from_str("acme/db").unwrap(),
+            PackageIdent::from_str("acme/cron").unwrap(),
+        ];
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            String::from("web"),
+            MemberOverrides {
+                channel: MemberValue::from("inherit"),
+                ..Default::default()
+            },
+        );
+        overrides.insert(
+            String::from("db"),
+            MemberOverrides {
+                channel: MemberValue::from("stable"),
+                ..Default::default()
+            },
+        );
+        overrides.insert(
+            String::from("cron"),
+            MemberOverrides {
+                channel: MemberValue::from(""),
+                ..Default::default()
+            },
+        );
+
+        let expanded = expand_composite_with_overrides(
+            &base,
+            "stack",
+            services,
+            BindMap::new(),
+            None,
+            &overrides,
+        ).unwrap();
+
+        assert_eq!(expanded[0].channel, "unstable");
+        assert_eq!(expanded[1].channel, "stable");
+        assert_eq!(expanded[2].channel, "");
+        assert!(expanded.iter().all(|spec| spec.group == "default"));
+    }
+
+    #[test]
+    fn expand_composite_member_group_override() {
+        let base = ServiceSpec::default();
+        let services = vec![
+            PackageIdent::from_str("acme/web").unwrap(),
+            PackageIdent::from_str("acme/db").unwrap(),
+        ];
+        let overrides_with = |group: &str| {
+            let mut overrides = HashMap::new();
+            overrides.insert(
+                String::from("db"),
+                MemberOverrides {
+                    group: MemberValue::from(group),
+                    ..Default::default()
+                },
+            );
+            overrides
+        };
+
+        let expanded = expand_composite_with_overrides(
+            &base,
+            "stack",
+            services.clone(),
+            BindMap::new(),
+            None,
+            &overrides_with("default"),
+        ).unwrap();
+        assert!(expanded.iter().all(|spec| spec.group == "default"));
+
+        match expand_composite_with_overrides(
+            &base,
+            "stack",
+            services,
+            BindMap::new(),
+            None,
+            &overrides_with("prod"),
+        ) {
+            Err(e) => match e.err {
+                MemberGroupOverride { member, group, .. } => {
+                    assert_eq!("db", member);
+                    assert_eq!("prod", group);
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("A member shouldn't leave the composite's group"),
+        }
+    }
+
+    #[test]
+    fn bind_mappings_for_prefers_exact_match() {
+        let mapping = |satisfying_service: &str| {
+            vec![BindMapping {
+                bind_name: String::from("database"),
+                satisfying_service: PackageIdent::from_str(satisfying_service).unwrap(),
+            }]
+        };
+        let ident = PackageIdent::from_str("acme/web/1.0.0/20180101000000").unwrap();
+        let mut bind_map = BindMap::new();
+        bind_map.insert(PackageIdent::from_str("acme/web").unwrap(), mapping("acme/db"));
+        bind_map.insert(ident.clone(), mapping("acme/postgres"));
+
+        let mappings = bind_mappings_for(&bind_map, &ident).unwrap();
+
+        assert_eq!(
+            mappings[0].satisfying_service,
+            PackageIdent::from_str("acme/postgres").unwrap()
+        );
+    }
+
+    #[test]
+    fn service_spec_desired_state_default_round_trips() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.desired_state, DEFAULT_DESIRED_STATE);
+        assert_eq!(spec.desired_state, DesiredState::Up);
+        let rendered = spec.to_toml_string().unwrap();
+        assert!(rendered.contains(r#"desired_state = "up""#));
+        assert_eq!(
+            ServiceSpec::from_str(&rendered).unwrap().desired_state,
+            DEFAULT_DESIRED_STATE
+        );
+        let (migrated, _) = migrate_spec(toml).unwrap();
+        assert_eq!(migrated.desired_state, DEFAULT_DESIRED_STATE);
+    }
+
+    #[test]
+    fn service_spec_without_load_record_parses() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            group = "jobs"
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.last_loaded_at, None);
+        assert_eq!(spec.loaded_from, None);
+        let rendered = spec.to_toml_string().unwrap();
+        assert!(!rendered.contains("last_loaded_at"));
+        assert!(!rendered.contains("loaded_from"));
+    }
+
+    #[test]
+    fn normalize_spec_dir_rewrites_only_unsorted_specs() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let mut unsorted = ServiceSpec::default_for(PackageIdent::from_str("origin/web").unwrap());
+        unsorted.binds = vec![
+            ServiceBind::from_str("db:postgres.default").unwrap(),
+            ServiceBind::from_str("cache:redis.default").unwrap(),
+        ];
+        let unsorted_path = unsorted.path_in(tmpdir.path());
+        let content = unsorted
+            .to_toml_string()
+            .unwrap()
+            .replace(
+                r#"["cache:redis.default", "db:postgres.default"]"#,
+                r#"["db:postgres.default", "cache:redis.default"]"#,
+            );
+        assert!(content.contains(r#"["db:postgres.default", "cache:redis.default"]"#));
+        file_from_str(&unsorted_path, &content);
+        let canonical = ServiceSpec::default_for(PackageIdent::from_str("origin/db").unwrap());
+        let canonical_path = canonical.path_in(tmpdir.path());
+        canonical.to_file(&canonical_path).unwrap();
+        let canonical_modified = fs::metadata(&canonical_path).unwrap().modified().unwrap();
+
+        let normalized = normalize_spec_dir(tmpdir.path()).unwrap();
+
+        assert_eq!(normalized, vec![unsorted_path.clone()]);
+        let mut rewritten = String::new();
+        File::open(&unsorted_path)
+            .unwrap()
+            .read_to_string(&mut rewritten)
+            .unwrap();
+        assert_eq!(rewritten, unsorted.to_toml_string().unwrap());
+        assert_eq!(
+            canonical_modified,
+            fs::metadata(&canonical_path).unwrap().modified().unwrap()
+        );
+        assert!(normalize_spec_dir(tmpdir.path()).unwrap().is_empty());
+    }
+
+    fn specs_matching_fixture() -> TempDir {
+        let tmpdir = TempDir::new("specs").unwrap();
+        for ident in &["core/redis/4.0.10", "core/postgresql", "acme/redis"] {
+            let spec = ServiceSpec::default_for(PackageIdent::from_str(ident).unwrap());
+            let path = tmpdir
+                .path()
+                .join(format!("{}-{}", spec.ident.origin, spec.file_name()));
+            spec.to_file(path).unwrap();
+        }
+        tmpdir
+    }
+
+    #[test]
+    fn specs_matching_origin() {
+        let tmpdir = specs_matching_fixture();
+
+        let specs = specs_matching(tmpdir.path(), "core/*").unwrap();
+        let mut idents: Vec<String> = specs.iter().map(|s| s.ident.to_string()).collect();
+        idents.sort();
+        assert_eq!(idents, vec!["core/postgresql", "core/redis/4.0.10"]);
+    }
+
+    #[test]
+    fn specs_matching_origin_and_name() {
+        let tmpdir = specs_matching_fixture();
+
+        let specs = specs_matching(tmpdir.path(), "core/redis").unwrap();
+        let idents: Vec<String> = specs.iter().map(|s| s.ident.to_string()).collect();
+        assert_eq!(idents, vec!["core/redis/4.0.10"]);
+
+        assert!(
+            specs_matching(tmpdir.path(), "core/redis/5.0.0")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn validate_spec_dir_reports_each_file() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let valid = ServiceSpec::default_for(PackageIdent::from_str("origin/valid").unwrap());
+        let valid_path = tmpdir.path().join(valid.file_name());
+        valid.to_file(&valid_path).unwrap();
+        let duplicate_path = tmpdir.path().join("duplicate.spec");
+        file_from_str(
+            &duplicate_path,
+            r#"
+            ident = "origin/duplicate"
+            binds = ["db:postgres.app", "db:mysql.app"]
+            "#,
+        );
+        let unparseable_path = tmpdir.path().join("unparseable.spec");
+        file_from_str(&unparseable_path, "ident = ");
+
+        let mut results = validate_spec_dir(tmpdir.path());
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0, duplicate_path);
+        match results[0].1 {
+            Err(ref e) => match e.err {
+                DuplicateBind(ref name) => assert_eq!("db", name),
+                ref wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail validation"),
+        }
+        assert_eq!(results[1].0, unparseable_path);
+        match results[1].1 {
+            Err(ref e) => match e.err {
+                ServiceSpecParse(_) => assert!(true),
+                ref wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should fail to parse"),
+        }
+        assert_eq!(results[2].0, valid_path);
+        assert!(results[2].1.is_ok());
+    }
+
+    #[test]
+    fn migrate_spec_defaults_empty_channel() {
+        let (spec, notes) = migrate_spec(
+            r#"
+            ident = "origin/legacy"
+            group = "default"
+            bldr_url = "https://bldr.habitat.sh"
+            channel = ""
+            topology = "standalone"
+            update_strategy = "none"
+            binding_mode = "strict"
+            desired_state = "up"
+            "#,
+        ).unwrap();
+
+        assert_eq!(&spec.channel, "stable");
+        assert_eq!(
+            notes,
+            vec![MigrationNote {
+                field: SpecField::Channel,
+                message: String::from("channel was empty, defaulted to 'stable'"),
+            }]
+        );
+    }
+
+    #[test]
+    fn migrate_spec_notes_missing_fields() {
+        let (spec, notes) = migrate_spec(r#"ident = "origin/legacy""#).unwrap();
+        let fields: Vec<SpecField> = notes.iter().map(|n| n.field).collect();
+
+        assert_eq!(spec.binding_mode, BindingMode::Strict);
+        assert!(fields.contains(&SpecField::Channel));
+        assert!(fields.contains(&SpecField::BindingMode));
+        assert!(!fields.contains(&SpecField::Ident));
+    }
+
+    #[test]
+    fn service_spec_from_file_missing() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("nope.spec");
+
+        match ServiceSpec::from_file(&path) {
+            Err(e) => match e.err {
+                ServiceSpecFileIO(p, _) => assert_eq!(path, p),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("File should not exist for read"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_file_empty() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("empty.spec");
+        file_from_str(&path, "");
+
+        match ServiceSpec::from_file(&path) {
+            Err(e) => match e.err {
+                MissingRequiredIdent => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("File should not exist for read"),
+        }
+    }
+
+    #[test]
+    fn service_spec_from_file_bad_contents() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("bad.spec");
+        file_from_str(&path, "You're gonna have a bad time");
+
+        match ServiceSpec::from_file(&path) {
+            Err(e) => match e.err {
+                ServiceSpecParse(_) => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("File should not exist for read"),
+        }
+    }
+
+    #[test]
+    fn detect_spec_version_legacy() {
+        let toml = r#"
+            ident = "origin/name"
+            "#;
+
+        assert_eq!(detect_spec_version(toml), SpecVersion::Legacy);
+    }
+
+    #[test]
+    fn detect_spec_version_versioned() {
+        let toml = r#"
+            format_version = 1
+            ident = "origin/name"
+            "#;
+
+        assert_eq!(detect_spec_version(toml), SpecVersion::Versioned(1));
+        assert_eq!(
+            ServiceSpec::from_reader(Cursor::new(toml)).unwrap().ident,
+            PackageIdent::from_str("origin/name").unwrap()
+        );
+    }
+
+    #[test]
+    fn service_spec_from_file_newer_format() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("name.spec");
+        file_from_str(
+            &path,
+            &format!("format_version = {}\nident = \"origin/name\"", SPEC_FORMAT_VERSION + 1),
+        );
+
+        match ServiceSpec::from_file(&path) {
+            Err(e) => match e.err {
+                UnsupportedSpecFormat(version) => assert_eq!(SPEC_FORMAT_VERSION + 1, version),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec in a newer format should fail to load"),
+        }
+    }
+
+    #[test]
+    fn service_spec_preserves_unknown_keys() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            group = "jobs"
+            from_the_future = "keep me"
+
+            [future_table]
+            answer = 42
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(
+            spec.extra.get("from_the_future"),
+            Some(&toml::Value::String(String::from("keep me")))
+        );
+        assert!(spec.extra.get("group").is_none());
+
+        spec.to_file(&path).unwrap();
+        let reloaded = ServiceSpec::from_file(&path).unwrap();
+
+        assert_eq!(spec, reloaded);
+        assert_eq!(spec.extra, reloaded.extra);
+        assert_eq!(&reloaded.group, "jobs");
+        assert_eq!(
+            reloaded
+                .extra
+                .get("future_table")
+                .and_then(|t| t.get("answer"))
+                .and_then(toml::Value::as_integer),
+            Some(42)
+        );
+    }
+
+    #[test]
+    fn service_spec_to_file() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let spec = ServiceSpecBuilder::new()
+            .ident(PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap())
+            .group("jobs")
+            .application_environment(
+                ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),
+            )
+            .bldr_url("http://example.com/depot")
+            .channel("unstable")
+            .topology(Topology::Leader)
+            .update_strategy(UpdateStrategy::AtOnce)
+            .binds(vec![
+                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
+                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+            ])
+            .binding_mode(BindingMode::Relaxed)
+            .config_from("/only/for/development")
+            .desired_state(DesiredState::Down)
+            .build()
+            .unwrap();
+        spec.to_file(&path).unwrap();
+        let toml = string_from_file(path);
+
+        assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
+        assert!(toml.contains(r#"group = "jobs""#));
+        assert!(toml.contains(r#"application_environment = "theinternet.preprod""#,));
+        assert!(toml.contains(r#"bldr_url = "http://example.com/depot""#));
+        assert!(toml.contains(r#"channel = "unstable""#));
+        assert!(toml.contains(r#"topology = "leader""#));
+        assert!(toml.contains(r#"update_strategy = "at-once""#));
+        assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
+        assert!(toml.contains(r#""db:postgres.app@acmecorp""#));
+        assert!(toml.contains(r#"desired_state = "down""#));
+        assert!(toml.contains(r#"config_from = "/only/for/development""#));
+        assert!(toml.contains(r#"binding_mode = "relaxed""#));
+    }
+
+    #[test]
+    fn service_spec_to_file_concurrent_writes_are_atomic() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let mut spec = ServiceSpec::default_for(
+            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+        );
+        spec.binds = vec![
+            ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
+            ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+        ];
+        spec.to_file(&path).unwrap();
+
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let path = path.clone();
+                let mut spec = spec.clone();
+                spec.group = format!("group{}", i);
+                thread::spawn(move || {
+                    for _ in 0..25 {
+                        spec.to_file(&path).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..100 {
+            let read = ServiceSpec::from_file(&path).expect("spec file should always parse");
+            assert_eq!(read.ident, spec.ident);
+            assert_eq!(read.binds, spec.binds);
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let leftovers: Vec<_> = fs::read_dir(tmpdir.path()).unwrap().collect();
+        assert_eq!(leftovers.len(), 1, "no temporary files should remain");
+    }
+
+    #[test]
+    fn service_spec_to_file_with_header() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("acme/web").unwrap());
+        spec.binds = vec![ServiceBind::from_str("db:postgres.default").unwrap()];
+        let path = spec.path_in(tmpdir.path());
+
+        spec.to_file_with_header(&path).unwrap();
+
+        let mut contents = String::new();
+        File::open(&path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert!(contents.starts_with("# Service spec for acme/web, written by"));
+        assert_eq!(ServiceSpec::from_file(&path).unwrap(), spec);
+    }
+
+    #[test]
+    fn service_spec_rename_on_disk() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("acme/old").unwrap());
+        let old_path = tmpdir.path().join(spec.file_name());
+        spec.to_file(&old_path).unwrap();
+
+        spec.ident = PackageIdent::from_str("acme/new").unwrap();
+        spec.rename_on_disk(&old_path).unwrap();
+
+        assert!(!old_path.exists());
+        let renamed = ServiceSpec::from_file(tmpdir.path().join("new.spec")).unwrap();
+        assert_eq!(renamed, spec);
+    }
+
+    #[test]
+    fn service_spec_rename_on_disk_missing_old_file() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("acme/new").unwrap());
+        let old_path = tmpdir.path().join("old.spec");
+
+        match spec.rename_on_disk(&old_path) {
+            Err(e) => match e.err {
+                ServiceSpecFileIO(p, _) => assert_eq!(old_path, p),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Old spec file should not exist"),
+        }
+        assert!(!tmpdir.path().join("new.spec").exists());
+    }
+
+    #[test]
+    fn service_spec_set_desired_state_on_disk() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let spec = ServiceSpecBuilder::new()
+            .ident(PackageIdent::from_str("acme/web").unwrap())
+            .group("prod")
+            .channel("unstable")
+            .topology(Topology::Leader)
+            .update_strategy(UpdateStrategy::Rolling)
+            .binds(vec![ServiceBind::from_str("db:postgres.prod").unwrap()])
+            .binding_mode(BindingMode::Relaxed)
+            .config_from("/only/for/development")
+            .build()
+            .unwrap();
+        let path = spec.path_in(tmpdir.path());
+        spec.to_file(&path).unwrap();
+
+        ServiceSpec::set_desired_state_on_disk(&path, DesiredState::Down).unwrap();
+
+        let flipped = ServiceSpec::from_file(&path).unwrap();
+        assert_eq!(flipped.desired_state, DesiredState::Down);
+        assert_eq!(
+            flipped,
+            ServiceSpec {
+                desired_state: DesiredState::Down,
+                ..spec
+            }
+        );
+    }
+
+    #[test]
+    fn service_spec_to_file_invalid_ident() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("name.spec");
+        // Remember: the default implementation of `PackageIdent` is an invalid identifier, missing
+        // origin and name--we're going to exploit this here
+        let spec = ServiceSpec::default();
+
+        match spec.to_file(path) {
+            Err(e) => match e.err {
+                MissingRequiredIdent => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Service spec file should not have been written"),
+        }
+    }
+
+    #[test]
+    fn service_spec_file_name() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa/1.2.3").unwrap());
+
+        assert_eq!(String::from("hoopa.spec"), spec.file_name());
+    }
+
+    #[test]
+    fn service_spec_path_in() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa/1.2.3").unwrap());
+
+        let dir = Path::new("/hab/sup/default/specs");
+        let path = spec.path_in(dir);
+
+        assert_eq!(path.parent().unwrap(), dir);
+        assert_eq!(path.file_name().unwrap(), "hoopa.spec");
+        assert_eq!(path.extension().unwrap(), "spec");
+    }
+
+    #[test]
+    fn service_spec_diff_identical() {
+        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+
+        assert_eq!(spec.diff(&spec.clone()), vec![]);
+    }
+
+    #[test]
+    fn service_spec_diff_changed_fields() {
+        let old = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        let mut new = old.clone();
+        new.channel = String::from("unstable");
+        new.topology = Topology::Leader;
+
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                SpecFieldChange::Channel(String::from("stable"), String::from("unstable")),
+                SpecFieldChange::Topology(String::from("standalone"), String::from("leader")),
+            ]
+        );
+    }
+
+    #[test]
+    fn service_spec_diff_reordered_binds() {
+        let mut old = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        old.binds = vec![
+            ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
+            ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+        ];
+        let mut new = old.clone();
+        new.binds.reverse();
+
+        assert_eq!(old.diff(&new), vec![]);
+    }
+
+    #[test]
+    fn service_spec_diff_only_desired_state() {
+        let old = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        let mut new = old.clone();
+        new.desired_state = DesiredState::Down;
+        let changes = old.diff(&new);
+
+        assert_eq!(
+            changes,
+            vec![SpecFieldChange::DesiredState(
+                String::from("up"),
+                String::from("down"),
+            )]
+        );
+        assert!(!changes.iter().any(SpecFieldChange::is_restart_relevant));
+    }
+
+    #[test]
+    fn service_spec_merge_from_no_fields() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        let original = spec.clone();
+        let mut other = ServiceSpec::default_for(PackageIdent::from_str("origin/other").unwrap());
+        other.channel = String::from("unstable");
+
+        spec.merge_from(&other, &[]);
+
+        assert_eq!(spec, original);
+    }
+
+    #[test]
+    fn service_spec_merge_from_channel_only() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        spec.topology = Topology::Leader;
+        spec.binds = vec![ServiceBind::from_str("cache:redis.cache").unwrap()];
+        let mut other = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        other.channel = String::from("unstable");
+
+        spec.merge_from(&other, &[SpecField::Channel]);
+
+        assert_eq!(&spec.channel, "unstable");
+        assert_eq!(spec.topology, Topology::Leader);
+        assert_eq!(
+            spec.binds,
+            vec![ServiceBind::from_str("cache:redis.cache").unwrap()]
+        );
+    }
+
+    #[test]
+    fn service_spec_merge_from_binds_replaces() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        spec.binds = vec![ServiceBind::from_str("cache:redis.cache").unwrap()];
+        let mut other = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa").unwrap());
+        other.binds = vec![ServiceBind::from_str("db:postgres.app").unwrap()];
+
+        spec.merge_from(&other, &[SpecField::Binds]);
+
+        assert_eq!(
+            spec.binds,
+            vec![ServiceBind::from_str("db:postgres.app").unwrap()]
+        );
+    }
+
+    #[test]
+    fn load_bind_map_from_file() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("binds.toml");
+        file_from_str(
+            &path,
+            r#"
+            ["core/web"]
+            database = "core/postgresql"
+            cache = "core/redis"
+
+            ["core/worker"]
+            queue = "core/rabbitmq"
+            "#,
+        );
+
+        let bind_map = load_bind_map(&path).unwrap();
+
+        assert_eq!(bind_map.len(), 2);
+        let web = &bind_map[&PackageIdent::from_str("core/web").unwrap()];
+        assert_eq!(web.len(), 2);
+        assert_eq!(web[0].bind_name, "cache");
+        assert_eq!(
+            web[0].satisfying_service,
+            PackageIdent::from_str("core/redis").unwrap()
+        );
+        assert_eq!(web[1].bind_name, "database");
+        let worker = &bind_map[&PackageIdent::from_str("core/worker").unwrap()];
+        assert_eq!(worker.len(), 1);
+        assert_eq!(
+            worker[0].satisfying_service,
+            PackageIdent::from_str("core/rabbitmq").unwrap()
+        );
+    }
+
+    #[test]
+    fn load_bind_map_invalid_ident() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("binds.toml");
+        file_from_str(
+            &path,
+            r#"
+            ["not-an-ident"]
+            database = "core/postgresql"
+            "#,
+        );
+
+        match load_bind_map(&path) {
+            Err(e) => match e.err {
+                InvalidBindMap(p, msg) => {
+                    assert_eq!(path, p);
+                    assert!(msg.contains("not-an-ident"));
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Bind map should fail to load"),
+        }
+    }
+
+    #[test]
+    fn set_composite_binds_reports_unknown_members() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("core/app").unwrap());
+        let mut bind_map = BindMap::new();
+        let binds = vec![
+            ServiceBind::from_str("wrongname:cache:redis.cache").unwrap(),
+            ServiceBind::from_str("other:cache:redis.cache").unwrap(),
+            ServiceBind::from_str("app:db:postgres.default").unwrap(),
+        ];
+        let member_names: HashSet<String> = vec![String::from("app"), String::from("other")]
+            .into_iter()
+            .collect();
+
+        let unknown = set_composite_binds(&mut spec, &mut bind_map, &binds, &member_names);
+
+        assert_eq!(
+            unknown,
+            vec![ServiceBind::from_str("wrongname:cache:redis.cache").unwrap()]
+        );
+        assert_eq!(
+            spec.binds,
+            vec![ServiceBind::from_str("app:db:postgres.default").unwrap()]
+        );
     }
-}
 
-/// Generate the binds for a composite's service, taking into account
-/// both the values laid out in composite definition and any CLI value
-/// the user may have specified. This allows the user to override a
-/// composite-defined bind, but also (perhaps more usefully) to
-/// declare binds for services within the composite that are not
-/// themselves *satisfied* by other members of the composite.
-///
-/// The final list of bind mappings is generated and then set in the
-/// `ServiceSpec`. Any binds that may have been present in the spec
-/// before are completely ignored.
-///
-/// # Parameters
-///
-/// * bind_map: output of package.bind_map()
-/// * cli_binds: per-service overrides given on the CLI
-fn set_composite_binds(spec: &mut ServiceSpec, bind_map: &mut BindMap, binds: &Vec<ServiceBind>) {
-    // We'll be layering bind specifications from the composite
-    // with any additional ones from the CLI. We'll store them here,
-    // keyed to the bind name
-    let mut final_binds: HashMap<String, ServiceBind> = HashMap::new();
+    #[test]
+    fn service_bind_from_str() {
+        let bind_str = "name:app.env#service.group@organization";
+        let bind = ServiceBind::from_str(bind_str).unwrap();
 
-    // First, generate the binds from the composite
-    if let Some(bind_mappings) = bind_map.remove(&spec.ident) {
-        // Turn each BindMapping into a ServiceBind
+        assert_eq!(bind.name, String::from("name"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("app.env#service.group@organization").unwrap()
+        );
+    }
 
-        // NOTE: We are explicitly NOT generating binds that include
-        // "organization". This is a feature that never quite found
-        // its footing, and will likely be removed / greatly
-        // overhauled Real Soon Now (TM) (as of September 2017).
-        //
-        // As it exists right now, "organization" is a supervisor-wide
-        // setting, and thus is only available for `hab sup run`.
-        // We don't have a way from `hab svc load` to access the organization setting of an
-        // active supervisor, and so we can't generate binds that include organizations.
-        for bind_mapping in bind_mappings.iter() {
-            let group = ServiceGroup::new(
-                spec.application_environment.as_ref(),
-                &bind_mapping.satisfying_service.name,
-                &spec.group,
-                None, // <-- organization
-            ).expect(
-                "Failed to parse bind mapping into service group. Did you validate your input?",
-            );
-            let bind = ServiceBind {
-                name: bind_mapping.bind_name.clone(),
-                service_group: group,
-                service_name: Some(bind_mapping.bind_name.clone()),
-            };
-            final_binds.insert(bind.name.clone(), bind);
+    #[test]
+    fn service_bind_from_str_simple() {
+        let bind_str = "name:service.group";
+        let bind = ServiceBind::from_str(bind_str).unwrap();
+
+        assert_eq!(bind.name, String::from("name"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("service.group").unwrap()
+        );
+    }
+
+    #[test]
+    fn service_bind_from_str_equals_separator() {
+        let bind = ServiceBind::from_str("name=service.group").unwrap();
+
+        assert_eq!(bind, ServiceBind::from_str("name:service.group").unwrap());
+        assert_eq!("name:service.group", bind.to_string());
+    }
+
+    #[test]
+    fn service_bind_from_str_composite_equals_separator() {
+        let bind = ServiceBind::from_str("service:name=app.env#service.group").unwrap();
+
+        assert_eq!(bind.service_name, Some(String::from("service")));
+        assert_eq!(bind.name, String::from("name"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("app.env#service.group").unwrap()
+        );
+        assert_eq!("service:name:app.env#service.group", bind.to_string());
+        assert_eq!(bind, ServiceBind::from_str(&bind.to_string()).unwrap());
+    }
+
+    #[test]
+    fn service_bind_from_str_equals_not_final_separator() {
+        for bind_str in &["service=name:service.group", "service=name=service.group"] {
+            match ServiceBind::from_str(bind_str) {
+                Err(e) => match e.err {
+                    InvalidBinding(val) => assert_eq!(*bind_str, val),
+                    wrong => panic!("Unexpected error returned: {:?}", wrong),
+                },
+                Ok(_) => panic!("String should fail to parse"),
+            }
         }
     }
 
-    // If anything was overridden or added on the CLI, layer that on
-    // now as well. These will take precedence over anything in the
-    // composite itself.
-    //
-    // Note that it consumes the values from cli_binds
-    for bind in binds
-        .iter()
-        .filter(|bind| bind.service_name.as_ref().unwrap() == &spec.ident.name)
-    {
-        final_binds.insert(bind.name.clone(), bind.clone());
+    #[test]
+    fn service_bind_from_str_quoted_name() {
+        let bind_str = r#""my:name":service.group"#;
+        let bind = ServiceBind::from_str(bind_str).unwrap();
+
+        assert_eq!(bind.name, String::from("my:name"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("service.group").unwrap()
+        );
+        assert_eq!(bind_str, bind.to_string());
+        assert_eq!(bind, ServiceBind::from_str(&bind.to_string()).unwrap());
     }
 
-    // Now take all the ServiceBinds we've collected.
-    spec.binds = final_binds.drain().map(|(_, v)| v).collect();
-}
+    #[test]
+    fn service_bind_from_str_quoted_name_composite() {
+        let bind = ServiceBind::from_str(r#"service:"a=b"=service.group"#).unwrap();
 
-#[cfg(test)]
-mod test {
-    use std::fs::{self, File};
-    use std::io::{BufReader, Read, Write};
-    use std::path::{Path, PathBuf};
-    use std::str::FromStr;
+        assert_eq!(bind.service_name, Some(String::from("service")));
+        assert_eq!(bind.name, String::from("a=b"));
+        assert_eq!(r#"service:"a=b":service.group"#, bind.to_string());
+    }
 
-    use hcore::error::Error as HError;
-    use hcore::package::PackageIdent;
-    use hcore::service::{ApplicationEnvironment, ServiceGroup};
-    use tempdir::TempDir;
-    use toml;
+    #[test]
+    fn service_bind_from_str_quoted_name_without_quotes_needed() {
+        let bind = ServiceBind::from_str(r#""name":service.group"#).unwrap();
 
-    use super::*;
-    use error::Error::*;
+        assert_eq!(bind.name, String::from("name"));
+        assert_eq!("name:service.group", bind.to_string());
+    }
 
-    fn file_from_str<P: AsRef<Path>>(path: P, content: &str) {
-        fs::create_dir_all(
-            path.as_ref()
-                .parent()
-                .expect("failed to determine file's parent directory"),
-        ).expect("failed to create parent directory recursively");
-        let mut file = File::create(path).expect("failed to create file");
-        file.write_all(content.as_bytes())
-            .expect("failed to write content to file");
+    #[test]
+    fn service_bind_from_str_unterminated_quote() {
+        for bind_str in &[r#""my:name:service.group"#, r#""my"name:service.group"#] {
+            match ServiceBind::from_str(bind_str) {
+                Err(e) => match e.err {
+                    InvalidBinding(val) => assert_eq!(*bind_str, val),
+                    wrong => panic!("Unexpected error returned: {:?}", wrong),
+                },
+                Ok(_) => panic!("String should fail to parse"),
+            }
+        }
     }
 
-    fn string_from_file<P: AsRef<Path>>(path: P) -> String {
-        let file = File::open(path).expect("failed to open file");
-        let mut file = BufReader::new(file);
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)
-            .expect("cannot read file to string");
-        buf
+    #[test]
+    fn service_bind_from_str_missing_colon() {
+        let bind_str = "uhoh";
+
+        match ServiceBind::from_str(bind_str) {
+            Err(e) => match e.err {
+                InvalidBinding(val) => assert_eq!("uhoh", val),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("String should fail to parse"),
+        }
     }
 
     #[test]
-    fn service_spec_from_str() {
-        let toml = r#"
-            ident = "origin/name/1.2.3/20170223130020"
-            group = "jobs"
-            application_environment = "theinternet.preprod"
-            bldr_url = "http://example.com/depot"
-            topology = "leader"
-            update_strategy = "rolling"
-            binds = ["cache:redis.cache@acmecorp", "db:postgres.app@acmecorp"]
-            config_from = "/only/for/development"
+    fn service_bind_from_str_too_many_colons() {
+        let bind_str = "uhoh:this:is:bad";
 
-            extra_stuff = "should be ignored"
-            "#;
-        let spec = ServiceSpec::from_str(toml).unwrap();
+        match ServiceBind::from_str(bind_str) {
+            Err(e) => match e.err {
+                InvalidBinding(val) => assert_eq!("uhoh:this:is:bad", val),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("String should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_bind_from_str_invalid_service_group() {
+        let bind_str = "uhoh:nosuchservicegroup@nope";
 
+        match ServiceBind::from_str(bind_str) {
+            Err(e) => match e.err {
+                HabitatCore(HError::InvalidServiceGroup(val)) => {
+                    assert_eq!("nosuchservicegroup@nope", val)
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("String should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_bind_from_str_bracketed_token() {
+        let bind_str = "name:[fe80::1]:service.group";
+        let bind = ServiceBind::from_str(bind_str).unwrap();
+
+        assert_eq!(bind.service_name, Some(String::from("name")));
+        assert_eq!(bind.name, String::from("[fe80::1]"));
         assert_eq!(
-            spec.ident,
-            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap()
+            bind.service_group,
+            ServiceGroup::from_str("service.group").unwrap()
+        );
+    }
+
+    #[test]
+    fn service_bind_from_str_bracketed_token_round_trip() {
+        let bind_str = "name:[fe80::1]:service.group";
+        let bind = ServiceBind::from_str(bind_str).unwrap();
+
+        assert_eq!(bind_str, bind.to_string());
+        assert_eq!(bind, ServiceBind::from_str(&bind.to_string()).unwrap());
+    }
+
+    #[test]
+    fn service_bind_from_str_oversized() {
+        let bind_str = format!("name:{}.group", "a".repeat(MAX_BIND_LEN));
+
+        match ServiceBind::from_str(&bind_str) {
+            Err(e) => match e.err {
+                InvalidBinding(val) => {
+                    assert!(val.len() < 100);
+                    assert!(val.starts_with("name:aaaa"));
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("String should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_bind_from_str_unbalanced_brackets() {
+        let bind_str = "name:[fe80::1:service.group";
+
+        match ServiceBind::from_str(bind_str) {
+            Err(e) => match e.err {
+                InvalidBinding(val) => assert_eq!("name:[fe80::1:service.group", val),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("String should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_bind_from_str_with_default_group_shorthand() {
+        let default = ServiceGroup::from_str("app.env#myapp.prod@organization").unwrap();
+        let bind = ServiceBind::from_str_with_default_group("cache:redis", &default).unwrap();
+
+        assert_eq!(bind.name, String::from("cache"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("app.env#redis.prod@organization").unwrap()
+        );
+        assert_eq!(bind.service_name, None);
+    }
+
+    #[test]
+    fn service_bind_from_str_with_default_group_explicit_group() {
+        let default = ServiceGroup::from_str("app.env#myapp.prod@organization").unwrap();
+        let bind =
+            ServiceBind::from_str_with_default_group("cache:redis.default", &default).unwrap();
+
+        assert_eq!(bind.name, String::from("cache"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("redis.default").unwrap()
+        );
+    }
+
+    #[test]
+    fn service_bind_from_str_shorthand_still_invalid() {
+        match ServiceBind::from_str("cache:redis") {
+            Err(e) => match e.err {
+                HabitatCore(HError::InvalidServiceGroup(val)) => assert_eq!("redis", val),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("String should fail to parse"),
+        }
+    }
+
+    #[test]
+    fn service_bind_from_str_group_pattern() {
+        let bind = ServiceBind::from_str("cache:redis.*").unwrap();
+
+        assert!(bind.is_group_pattern());
+        assert_eq!(bind.service_group.service(), "redis");
+        assert_eq!(bind.service_group.group(), "*");
+        assert_eq!("cache:redis.*", bind.to_string());
+        assert!(!ServiceBind::from_str("cache:redis.default")
+            .unwrap()
+            .is_group_pattern());
+    }
+
+    #[test]
+    fn service_bind_resolve_group_pattern() {
+        let bind = ServiceBind::from_str("cache:redis.*").unwrap();
+        let live_groups: HashSet<ServiceGroup> = vec![
+            "redis.prod",
+            "redis.default",
+            "postgres.prod",
+            "redis.prod@acme",
+        ].into_iter()
+            .map(|group| ServiceGroup::from_str(group).unwrap())
+            .collect();
+
+        assert_eq!(
+            bind.resolve_group_pattern(&live_groups),
+            vec![
+                ServiceBind::from_str("cache:redis.default").unwrap(),
+                ServiceBind::from_str("cache:redis.prod").unwrap(),
+            ]
+        );
+
+        let concrete = ServiceBind::from_str("db:postgres.prod").unwrap();
+        assert_eq!(concrete.resolve_group_pattern(&live_groups), vec![concrete]);
+    }
+
+    #[test]
+    fn service_bind_matches_group_exactly() {
+        let bind = ServiceBind::from_str("db:app.env#postgres.default@acmecorp").unwrap();
+
+        assert!(bind.matches_group(
+            &ServiceGroup::from_str("app.env#postgres.default@acmecorp").unwrap()
+        ));
+    }
+
+    #[test]
+    fn service_bind_matches_group_differing_org() {
+        let bind = ServiceBind::from_str("db:postgres.default@acmecorp").unwrap();
+
+        assert!(!bind.matches_group(&ServiceGroup::from_str("postgres.default@other").unwrap()));
+        assert!(!bind.matches_group(&ServiceGroup::from_str("postgres.default").unwrap()));
+    }
+
+    #[test]
+    fn service_bind_matches_group_differing_application_environment() {
+        let bind = ServiceBind::from_str("db:app.env#postgres.default").unwrap();
+
+        assert!(!bind.matches_group(&ServiceGroup::from_str("app.prod#postgres.default").unwrap()));
+        assert!(!bind.matches_group(&ServiceGroup::from_str("postgres.default").unwrap()));
+    }
+
+    #[test]
+    fn service_bind_from_str_with_fallback() {
+        let bind = ServiceBind::from_str("db:postgres.primary|postgres.backup").unwrap();
+
+        assert_eq!(bind.name, String::from("db"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("postgres.primary").unwrap()
         );
-        assert_eq!(spec.group, String::from("jobs"));
         assert_eq!(
-            spec.application_environment,
-            Some(ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),)
+            bind.fallback_service_group,
+            Some(ServiceGroup::from_str("postgres.backup").unwrap())
         );
-        assert_eq!(spec.bldr_url, String::from("http://example.com/depot"));
-        assert_eq!(spec.topology, Topology::Leader);
-        assert_eq!(spec.update_strategy, UpdateStrategy::Rolling);
+        assert_eq!("db:postgres.primary|postgres.backup", bind.to_string());
+    }
+
+    #[test]
+    fn merge_binds_override_existing_bind() {
+        let base = vec![
+            ServiceBind::from_str("db:postgres.default").unwrap(),
+            ServiceBind::from_str("cache:redis.default").unwrap(),
+        ];
+        let overrides = vec![ServiceBind::from_str("db:postgres.prod").unwrap()];
+
         assert_eq!(
-            spec.binds,
+            merge_binds(base, overrides),
             vec![
-                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
-                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+                ServiceBind::from_str("cache:redis.default").unwrap(),
+                ServiceBind::from_str("db:postgres.prod").unwrap(),
             ]
         );
-        assert_eq!(
-            spec.config_from,
-            Some(PathBuf::from("/only/for/development"))
-        );
     }
 
     #[test]
-    fn service_spec_from_str_missing_ident() {
-        let toml = r#""#;
+    fn merge_binds_adds_new_bind() {
+        let base = vec![ServiceBind::from_str("db:postgres.default").unwrap()];
+        let overrides = vec![
+            ServiceBind::from_str("queue:rabbitmq.default").unwrap(),
+            ServiceBind::from_str("auth:keycloak.default").unwrap(),
+        ];
 
-        match ServiceSpec::from_str(toml) {
-            Err(e) => match e.err {
-                MissingRequiredIdent => assert!(true),
-                e => panic!("Unexpected error returned: {:?}", e),
-            },
-            Ok(_) => panic!("Spec TOML should fail to parse"),
-        }
+        assert_eq!(
+            merge_binds(base, overrides),
+            vec![
+                ServiceBind::from_str("auth:keycloak.default").unwrap(),
+                ServiceBind::from_str("db:postgres.default").unwrap(),
+                ServiceBind::from_str("queue:rabbitmq.default").unwrap(),
+            ]
+        );
     }
 
     #[test]
-    fn service_spec_from_str_invalid_topology() {
-        let toml = r#"
-            ident = "origin/name/1.2.3/20170223130020"
-            topology = "smartest-possible"
-            "#;
+    fn detect_bind_cycles_two_node_cycle() {
+        let mut web = ServiceSpec::default_for(PackageIdent::from_str("origin/web").unwrap());
+        web.binds = vec![ServiceBind::from_str("backend:api.default").unwrap()];
+        let mut api = ServiceSpec::default_for(PackageIdent::from_str("origin/api").unwrap());
+        api.binds = vec![ServiceBind::from_str("frontend:web.default").unwrap()];
 
-        match ServiceSpec::from_str(toml) {
-            Err(e) => match e.err {
-                ServiceSpecParse(_) => assert!(true),
-                e => panic!("Unexpected error returned: {:?}", e),
-            },
-            Ok(_) => panic!("Spec TOML should fail to parse"),
-        }
+        assert_eq!(
+            detect_bind_cycles(&[web, api]),
+            vec![vec![
+                ServiceGroup::from_str("web.default").unwrap(),
+                ServiceGroup::from_str("api.default").unwrap(),
+            ]]
+        );
     }
 
     #[test]
-    fn service_spec_from_str_invalid_binds() {
-        let toml = r#"
-            ident = "origin/name/1.2.3/20170223130020"
-            topology = "leader"
-            binds = ["magic:magicness.default", "winning"]
-            "#;
+    fn detect_bind_cycles_acyclic() {
+        let mut web = ServiceSpec::default_for(PackageIdent::from_str("origin/web").unwrap());
+        web.binds = vec![
+            ServiceBind::from_str("backend:api.default").unwrap(),
+            ServiceBind::from_str("db:postgres.default").unwrap(),
+        ];
+        let mut api = ServiceSpec::default_for(PackageIdent::from_str("origin/api").unwrap());
+        api.binds = vec![ServiceBind::from_str("db:postgres.default").unwrap()];
+        let db = ServiceSpec::default_for(PackageIdent::from_str("origin/postgres").unwrap());
 
-        match ServiceSpec::from_str(toml) {
-            Err(e) => match e.err {
-                ServiceSpecParse(_) => assert!(true),
-                e => panic!("Unexpected error returned: {:?}", e),
-            },
-            Ok(_) => panic!("Spec TOML should fail to parse"),
-        }
+        assert!(detect_bind_cycles(&[web, api, db]).is_empty());
     }
 
     #[test]
-    fn service_spec_to_toml_string() {
-        let spec = ServiceSpec {
-            ident: PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
-            group: String::from("jobs"),
-            application_environment: Some(
-                ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),
-            ),
-            bldr_url: String::from("http://example.com/depot"),
-            channel: String::from("unstable"),
-            topology: Topology::Leader,
-            update_strategy: UpdateStrategy::AtOnce,
-            binds: vec![
-                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
-                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
-            ],
-            binding_mode: BindingMode::Relaxed,
-            config_from: Some(PathBuf::from("/only/for/development")),
-            desired_state: DesiredState::Down,
-            svc_encrypted_password: None,
-            composite: None,
-        };
-        let toml = spec.to_toml_string().unwrap();
+    fn service_bind_normalized_drops_redundant_fallback() {
+        let redundant = ServiceBind::from_str("db:app.env#postgres.app|app.env#postgres.app");
+        let plain = ServiceBind::from_str("db:app.env#postgres.app").unwrap();
 
-        assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
-        assert!(toml.contains(r#"group = "jobs""#));
-        assert!(toml.contains(r#"application_environment = "theinternet.preprod""#,));
-        assert!(toml.contains(r#"bldr_url = "http://example.com/depot""#));
-        assert!(toml.contains(r#"channel = "unstable""#));
-        assert!(toml.contains(r#"topology = "leader""#));
-        assert!(toml.contains(r#"update_strategy = "at-once""#));
-        assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
-        assert!(toml.contains(r#""db:postgres.app@acmecorp""#));
-        assert!(toml.contains(r#"desired_state = "down""#));
-        assert!(toml.contains(r#"config_from = "/only/for/development""#));
-        assert!(toml.contains(r#"binding_mode = "relaxed""#));
+        assert_ne!(redundant.as_ref().unwrap(), &plain);
+        assert_eq!(redundant.unwrap().normalized(), plain.normalized());
+        assert_eq!(plain, plain.normalized());
+
+        let fallback = ServiceBind::from_str("db:postgres.app|postgres.default").unwrap();
+        assert_eq!(fallback, fallback.normalized());
     }
 
     #[test]
-    fn service_spec_to_toml_string_invalid_ident() {
-        // Remember: the default implementation of `PackageIdent` is an invalid identifier, missing
-        // origin and name--we're going to exploit this here
-        let spec = ServiceSpec::default();
+    fn service_spec_diff_ignores_equivalent_binds() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![ServiceBind::from_str("db:postgres.app").unwrap()];
+        let mut other = spec.clone();
+        other.binds = vec![ServiceBind::from_str("db:postgres.app|postgres.app").unwrap()];
 
-        match spec.to_toml_string() {
-            Err(e) => match e.err {
-                MissingRequiredIdent => assert!(true),
-                wrong => panic!("Unexpected error returned: {:?}", wrong),
-            },
-            Ok(_) => panic!("Spec TOML should fail to render"),
-        }
+        assert!(spec.diff(&other).is_empty());
     }
 
     #[test]
-    fn service_spec_from_file() {
-        let tmpdir = TempDir::new("specs").unwrap();
-        let path = tmpdir.path().join("name.spec");
-        let toml = r#"
-            ident = "origin/name/1.2.3/20170223130020"
-            group = "jobs"
-            application_environment = "theinternet.preprod"
-            bldr_url = "http://example.com/depot"
-            topology = "leader"
-            update_strategy = "rolling"
-            binds = ["cache:redis.cache@acmecorp", "db:postgres.app@acmecorp"]
-            config_from = "/only/for/development"
+    fn service_spec_bind_diff() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![
+            ServiceBind::from_str("db:postgres.app").unwrap(),
+            ServiceBind::from_str("cache:redis.app").unwrap(),
+            ServiceBind::from_str("queue:rabbitmq.app").unwrap(),
+        ];
+        let mut other = spec.clone();
+        other.binds = vec![
+            ServiceBind::from_str("db:postgres.app|postgres.app").unwrap(),
+            ServiceBind::from_str("cache:redis.sessions").unwrap(),
+            ServiceBind::from_str("auth:keycloak.app").unwrap(),
+        ];
 
-            extra_stuff = "should be ignored"
-            "#;
-        file_from_str(&path, toml);
-        let spec = ServiceSpec::from_file(path).unwrap();
+        let (added, removed) = spec.bind_diff(&other);
 
         assert_eq!(
-            spec.ident,
-            PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap()
-        );
-        assert_eq!(spec.group, String::from("jobs"));
-        assert_eq!(spec.bldr_url, String::from("http://example.com/depot"));
-        assert_eq!(spec.topology, Topology::Leader);
-        assert_eq!(
-            spec.application_environment,
-            Some(ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),)
+            added,
+            vec![
+                ServiceBind::from_str("cache:redis.sessions").unwrap(),
+                ServiceBind::from_str("auth:keycloak.app").unwrap(),
+            ]
         );
-        assert_eq!(spec.update_strategy, UpdateStrategy::Rolling);
         assert_eq!(
-            spec.binds,
+            removed,
             vec![
-                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
-                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
+                ServiceBind::from_str("cache:redis.app").unwrap(),
+                ServiceBind::from_str("queue:rabbitmq.app").unwrap(),
             ]
         );
-        assert_eq!(&spec.channel, "stable");
+        assert_eq!(other.bind_diff(&other), (vec![], vec![]));
+    }
+
+    #[test]
+    fn service_bind_parse_with_custom_delimiter() {
+        let syntax = BindSyntax::new('/');
+
+        let bind = ServiceBind::parse_with("db/postgres.app@acmecorp", &syntax).unwrap();
+        assert_eq!(bind, ServiceBind::from_str("db:postgres.app@acmecorp").unwrap());
+
+        let composite =
+            ServiceBind::parse_with("app/db/postgres.primary|postgres.backup", &syntax).unwrap();
         assert_eq!(
-            spec.config_from,
-            Some(PathBuf::from("/only/for/development"))
+            composite,
+            ServiceBind::from_str("app:db:postgres.primary|postgres.backup").unwrap()
         );
 
+        let name_with_colon = ServiceBind::parse_with("my:db/postgres.app", &syntax).unwrap();
+        assert_eq!(name_with_colon.name, "my:db");
+    }
+
+    #[test]
+    fn service_bind_format_with_custom_delimiter() {
+        let syntax = BindSyntax::new('/');
+        let bind = ServiceBind::from_str("app:db:postgres.primary|postgres.backup").unwrap();
+
+        assert_eq!(bind.format_with(&syntax), "app/db/postgres.primary|postgres.backup");
+        assert_eq!(bind.format_with(&BindSyntax::default()), bind.to_string());
+        let quoted = ServiceBind::parse_with("\"my/db\"/postgres.app", &syntax).unwrap();
+        assert_eq!(quoted.format_with(&syntax), "\"my/db\"/postgres.app");
         assert_eq!(
-            spec.binding_mode,
-            BindingMode::Strict,
-            "Strict is the default mode, if nothing was previously specified."
+            ServiceBind::parse_with(&bind.format_with(&syntax), &syntax).unwrap(),
+            bind
         );
     }
 
     #[test]
-    fn service_spec_from_file_missing() {
-        let tmpdir = TempDir::new("specs").unwrap();
-        let path = tmpdir.path().join("nope.spec");
+    fn service_bind_from_str_composite_with_fallback() {
+        let bind = ServiceBind::from_str("app:db:postgres.primary|postgres.backup").unwrap();
 
-        match ServiceSpec::from_file(&path) {
-            Err(e) => match e.err {
-                ServiceSpecFileIO(p, _) => assert_eq!(path, p),
-                wrong => panic!("Unexpected error returned: {:?}", wrong),
-            },
-            Ok(_) => panic!("File should not exist for read"),
-        }
+        assert_eq!(bind.service_name, Some(String::from("app")));
+        assert_eq!(
+            bind.fallback_service_group,
+            Some(ServiceGroup::from_str("postgres.backup").unwrap())
+        );
+        assert_eq!("app:db:postgres.primary|postgres.backup", bind.to_string());
     }
 
     #[test]
-    fn service_spec_from_file_empty() {
-        let tmpdir = TempDir::new("specs").unwrap();
-        let path = tmpdir.path().join("empty.spec");
-        file_from_str(&path, "");
+    fn service_bind_from_str_without_fallback() {
+        let bind = ServiceBind::from_str("db:postgres.primary").unwrap();
 
-        match ServiceSpec::from_file(&path) {
-            Err(e) => match e.err {
-                MissingRequiredIdent => assert!(true),
-                wrong => panic!("Unexpected error returned: {:?}", wrong),
-            },
-            Ok(_) => panic!("File should not exist for read"),
-        }
+        assert_eq!(bind.fallback_service_group, None);
+        assert_eq!("db:postgres.primary", bind.to_string());
     }
 
     #[test]
-    fn service_spec_from_file_bad_contents() {
-        let tmpdir = TempDir::new("specs").unwrap();
-        let path = tmpdir.path().join("bad.spec");
-        file_from_str(&path, "You're gonna have a bad time");
+    fn service_bind_from_str_too_many_fallbacks() {
+        let bind_str = "db:postgres.primary|postgres.backup|postgres.other";
 
-        match ServiceSpec::from_file(&path) {
+        match ServiceBind::from_str(bind_str) {
             Err(e) => match e.err {
-                ServiceSpecParse(_) => assert!(true),
+                InvalidBinding(val) => assert_eq!(bind_str, val),
                 wrong => panic!("Unexpected error returned: {:?}", wrong),
             },
-            Ok(_) => panic!("File should not exist for read"),
+            Ok(_) => panic!("String should fail to parse"),
         }
     }
 
     #[test]
-    fn service_spec_to_file() {
-        let tmpdir = TempDir::new("specs").unwrap();
-        let path = tmpdir.path().join("name.spec");
-        let spec = ServiceSpec {
-            ident: PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
-            group: String::from("jobs"),
-            application_environment: Some(
-                ApplicationEnvironment::from_str("theinternet.preprod").unwrap(),
-            ),
-            bldr_url: String::from("http://example.com/depot"),
-            channel: String::from("unstable"),
-            topology: Topology::Leader,
-            update_strategy: UpdateStrategy::AtOnce,
-            binds: vec![
-                ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
-                ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
-            ],
-            binding_mode: BindingMode::Relaxed,
-            config_from: Some(PathBuf::from("/only/for/development")),
-            desired_state: DesiredState::Down,
-            svc_encrypted_password: None,
-            composite: None,
-        };
-        spec.to_file(&path).unwrap();
-        let toml = string_from_file(path);
-
-        assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
-        assert!(toml.contains(r#"group = "jobs""#));
-        assert!(toml.contains(r#"application_environment = "theinternet.preprod""#,));
-        assert!(toml.contains(r#"bldr_url = "http://example.com/depot""#));
-        assert!(toml.contains(r#"channel = "unstable""#));
-        assert!(toml.contains(r#"topology = "leader""#));
-        assert!(toml.contains(r#"update_strategy = "at-once""#));
-        assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
-        assert!(toml.contains(r#""db:postgres.app@acmecorp""#));
-        assert!(toml.contains(r#"desired_state = "down""#));
-        assert!(toml.contains(r#"config_from = "/only/for/development""#));
-        assert!(toml.contains(r#"binding_mode = "relaxed""#));
-    }
-
-    #[test]
-    fn service_spec_to_file_invalid_ident() {
-        let tmpdir = TempDir::new("specs").unwrap();
-        let path = tmpdir.path().join("name.spec");
-        // Remember: the default implementation of `PackageIdent` is an invalid identifier, missing
-        // origin and name--we're going to exploit this here
-        let spec = ServiceSpec::default();
+    fn service_bind_from_str_with_port() {
+        let bind = ServiceBind::from_str("cache:redis.default:6380").unwrap();
 
-        match spec.to_file(path) {
-            Err(e) => match e.err {
-                MissingRequiredIdent => assert!(true),
-                wrong => panic!("Unexpected error returned: {:?}", wrong),
-            },
-            Ok(_) => panic!("Service spec file should not have been written"),
-        }
+        assert_eq!(bind.name, "cache");
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("redis.default").unwrap()
+        );
+        assert_eq!(bind.service_name, None);
+        assert_eq!(bind.port, Some(6380));
+        assert_eq!("cache:redis.default:6380", bind.to_string());
     }
 
     #[test]
-    fn service_spec_file_name() {
-        let spec = ServiceSpec::default_for(PackageIdent::from_str("origin/hoopa/1.2.3").unwrap());
+    fn service_bind_from_str_composite_with_port() {
+        let bind = ServiceBind::from_str("app:cache:redis.default:6380").unwrap();
 
-        assert_eq!(String::from("hoopa.spec"), spec.file_name());
+        assert_eq!(bind.service_name, Some(String::from("app")));
+        assert_eq!(bind.name, "cache");
+        assert_eq!(bind.port, Some(6380));
+        assert_eq!("app:cache:redis.default:6380", bind.to_string());
     }
 
     #[test]
-    fn service_bind_from_str() {
-        let bind_str = "name:app.env#service.group@organization";
-        let bind = ServiceBind::from_str(bind_str).unwrap();
+    fn service_bind_from_str_composite_without_port() {
+        let bind = ServiceBind::from_str("app:cache:redis.default").unwrap();
 
-        assert_eq!(bind.name, String::from("name"));
-        assert_eq!(
-            bind.service_group,
-            ServiceGroup::from_str("app.env#service.group@organization").unwrap()
-        );
+        assert_eq!(bind.service_name, Some(String::from("app")));
+        assert_eq!(bind.port, None);
+        assert_eq!("app:cache:redis.default", bind.to_string());
     }
 
     #[test]
-    fn service_bind_from_str_simple() {
-        let bind_str = "name:service.group";
-        let bind = ServiceBind::from_str(bind_str).unwrap();
+    fn service_bind_from_str_with_fallback_and_port() {
+        let bind = ServiceBind::from_str("cache:redis.primary|redis.backup:6380").unwrap();
 
-        assert_eq!(bind.name, String::from("name"));
         assert_eq!(
-            bind.service_group,
-            ServiceGroup::from_str("service.group").unwrap()
+            bind.fallback_service_group,
+            Some(ServiceGroup::from_str("redis.backup").unwrap())
         );
+        assert_eq!(bind.port, Some(6380));
+        assert_eq!("cache:redis.primary|redis.backup:6380", bind.to_string());
     }
 
     #[test]
-    fn service_bind_from_str_missing_colon() {
-        let bind_str = "uhoh";
+    fn service_bind_from_str_port_out_of_range() {
+        let bind_str = "cache:redis.default:65536";
 
         match ServiceBind::from_str(bind_str) {
             Err(e) => match e.err {
-                InvalidBinding(val) => assert_eq!("uhoh", val),
+                InvalidBinding(val) => assert_eq!(bind_str, val),
                 wrong => panic!("Unexpected error returned: {:?}", wrong),
             },
             Ok(_) => panic!("String should fail to parse"),
@@ -917,31 +6802,27 @@ mod test {
     }
 
     #[test]
-    fn service_bind_from_str_too_many_colons() {
-        let bind_str = "uhoh:this:is:bad";
+    fn service_bind_toml_round_trip_with_port() {
+        let mut spec = ServiceSpec::default_for(PackageIdent::from_str("origin/name").unwrap());
+        spec.binds = vec![ServiceBind::from_str("cache:redis.default:6380").unwrap()];
+        let toml = spec.to_toml_string().unwrap();
 
-        match ServiceBind::from_str(bind_str) {
-            Err(e) => match e.err {
-                InvalidBinding(val) => assert_eq!("uhoh:this:is:bad", val),
-                wrong => panic!("Unexpected error returned: {:?}", wrong),
-            },
-            Ok(_) => panic!("String should fail to parse"),
-        }
+        assert!(toml.contains(r#""cache:redis.default:6380""#));
+        assert_eq!(ServiceSpec::from_str(&toml).unwrap(), spec);
     }
 
     #[test]
-    fn service_bind_from_str_invalid_service_group() {
-        let bind_str = "uhoh:nosuchservicegroup@nope";
+    fn service_bind_service_name_or_composite() {
+        let bind = ServiceBind::from_str("app:db:postgres.default").unwrap();
 
-        match ServiceBind::from_str(bind_str) {
-            Err(e) => match e.err {
-                HabitatCore(HError::InvalidServiceGroup(val)) => {
-                    assert_eq!("nosuchservicegroup@nope", val)
-                }
-                wrong => panic!("Unexpected error returned: {:?}", wrong),
-            },
-            Ok(_) => panic!("String should fail to parse"),
-        }
+        assert_eq!(bind.service_name_or("web"), "app");
+    }
+
+    #[test]
+    fn service_bind_service_name_or_standard() {
+        let bind = ServiceBind::from_str("db:postgres.default").unwrap();
+
+        assert_eq!(bind.service_name_or("web"), "web");
     }
 
     #[test]
@@ -950,6 +6831,8 @@ mod test {
             name: String::from("name"),
             service_group: ServiceGroup::from_str("service.group").unwrap(),
             service_name: None,
+            fallback_service_group: None,
+            port: None,
         };
 
         assert_eq!("name:service.group", bind.to_string());
@@ -983,6 +6866,8 @@ mod test {
                 name: String::from("name"),
                 service_group: ServiceGroup::from_str("service.group").unwrap(),
                 service_name: None,
+                fallback_service_group: None,
+                port: None,
             },
         };
         let toml = toml::to_string(&data).unwrap();
@@ -12,17 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::error::Error as StdErr;
 use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use glob::glob;
+use hcore::os::process::{self, Pid};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 
 use error::{Error, Result};
@@ -32,6 +36,86 @@ static LOGKEY: &'static str = "SW";
 const WATCHER_DELAY_MS: u64 = 2_000;
 const SPEC_FILE_EXT: &'static str = "spec";
 const SPEC_FILE_GLOB: &'static str = "*.spec";
+const SPEC_FILE_RECURSIVE_GLOB: &'static str = "**/*.spec";
+const SPEC_DIR_LOCK_FILE: &'static str = ".spec-dir.lock";
+
+/// An exclusive lock on a spec directory, held for bulk operations such as migrations which
+/// must not race with another process writing the same specs. The lock is a file in the
+/// directory holding the owning process's PID, and is released when the guard is dropped.
+#[derive(Debug)]
+pub struct SpecDirLock {
+    path: PathBuf,
+}
+
+impl SpecDirLock {
+    /// Acquires the lock on `dir`. A lock left behind by a process which is no longer running,
+    /// or whose lock file can't be read, is treated as stale and replaced.
+    ///
+    /// # Errors
+    ///
+    /// * If another running process holds the lock
+    /// * If the lock file can't be written
+    pub fn acquire<T>(dir: T) -> Result<Self>
+    where
+        T: AsRef<Path>,
+    {
+        let path = dir.as_ref().join(SPEC_DIR_LOCK_FILE);
+        if let Ok(lock) = Self::create(&path) {
+            return Ok(lock);
+        }
+        if let Some(pid) = Self::holder(&path) {
+            if process::is_alive(pid) {
+                return Err(sup_error!(Error::SpecDirLocked(
+                    dir.as_ref().to_path_buf(),
+                    pid
+                )));
+            }
+        }
+        debug!("Replacing stale spec directory lock {}", path.display());
+        if let Err(err) = fs::remove_file(&path) {
+            debug!("Couldn't remove stale spec directory lock, {}", err);
+        }
+        Self::create(&path)
+    }
+
+    fn create(path: &Path) -> Result<Self> {
+        // The PID is written to a file of our own which is then linked into place, so the lock
+        // file never exists without its holder's PID in it. Otherwise another process could read
+        // it while it's still empty and take the live lock for a stale one.
+        let pid = process::current_pid();
+        let tmpfile = path.with_extension(format!("lock.{}", pid));
+        File::create(&tmpfile)
+            .and_then(|mut file| write!(&mut file, "{}", pid))
+            .map_err(|err| {
+                let _ = fs::remove_file(&tmpfile);
+                sup_error!(Error::SpecDirLockIO(path.to_path_buf(), err))
+            })?;
+        let linked = fs::hard_link(&tmpfile, path);
+        if let Err(err) = fs::remove_file(&tmpfile) {
+            debug!("Couldn't remove temporary spec directory lock, {}", err);
+        }
+        linked.map_err(|err| sup_error!(Error::SpecDirLockIO(path.to_path_buf(), err)))?;
+        Ok(SpecDirLock {
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn holder(path: &Path) -> Option<Pid> {
+        let mut content = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .ok()?;
+        content.trim().parse::<Pid>().ok()
+    }
+}
+
+impl Drop for SpecDirLock {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            debug!("Couldn't remove spec directory lock, {}", err);
+        }
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum SpecWatcherEvent {
@@ -52,18 +136,79 @@ impl SpecWatcher {
         Self::run_with::<RecommendedWatcher, _>(path)
     }
 
+    /// Returns the spec files in `watch_path`, sorted lexicographically by file stem so that
+    /// services are always loaded in the same order.
     pub fn spec_files<T>(watch_path: T) -> Result<Vec<PathBuf>>
     where
         T: AsRef<Path>,
     {
-        Ok(glob(&watch_path
-            .as_ref()
-            .join(SPEC_FILE_GLOB)
-            .display()
-            .to_string())?
+        Self::spec_files_with_ext(watch_path, SPEC_FILE_EXT)
+    }
+
+    /// Returns the spec files in `watch_path` as `spec_files` does, along with a `SpecDirLock` on
+    /// the directory. Holding the lock while reading the specs keeps a batch writer from
+    /// rewriting them part way through.
+    ///
+    /// # Errors
+    ///
+    /// * If `watch_path` is locked by another process
+    /// * If `watch_path` can't be read
+    pub fn spec_files_locked<T>(watch_path: T) -> Result<(SpecDirLock, Vec<PathBuf>)>
+    where
+        T: AsRef<Path>,
+    {
+        let lock = SpecDirLock::acquire(watch_path.as_ref())?;
+        let files = Self::spec_files(watch_path)?;
+        Ok((lock, files))
+    }
+
+    /// Returns the files in `watch_path` with the extension `ext`, given without a leading dot
+    /// (such as `spec.staged`), sorted lexicographically by file stem. This allows specs to be
+    /// staged under another extension before being renamed into place.
+    pub fn spec_files_with_ext<T>(watch_path: T, ext: &str) -> Result<Vec<PathBuf>>
+    where
+        T: AsRef<Path>,
+    {
+        Self::glob_spec_files(watch_path, &format!("*.{}", ext), |a, b| {
+            a.file_stem().cmp(&b.file_stem())
+        })
+    }
+
+    /// Returns the spec files in `watch_path` and all of its subdirectories, sorted by file stem
+    /// and then by path.
+    pub fn spec_files_recursive<T>(watch_path: T) -> Result<Vec<PathBuf>>
+    where
+        T: AsRef<Path>,
+    {
+        Self::glob_spec_files(watch_path, SPEC_FILE_RECURSIVE_GLOB, |a, b| {
+            a.file_stem()
+                .cmp(&b.file_stem())
+                .then_with(|| a.cmp(b))
+        })
+    }
+
+    /// Returns the spec files in `watch_path`, sorted with the given comparator.
+    pub fn spec_files_sorted_by<T, F>(watch_path: T, compare: F) -> Result<Vec<PathBuf>>
+    where
+        T: AsRef<Path>,
+        F: FnMut(&PathBuf, &PathBuf) -> cmp::Ordering,
+    {
+        Self::glob_spec_files(watch_path, SPEC_FILE_GLOB, compare)
+    }
+
+    fn glob_spec_files<T, F>(watch_path: T, pattern: &str, compare: F) -> Result<Vec<PathBuf>>
+    where
+        T: AsRef<Path>,
+        F: FnMut(&PathBuf, &PathBuf) -> cmp::Ordering,
+    {
+        let watch_path = watch_path.as_ref();
+        let mut files: Vec<PathBuf> = glob(&watch_path.join(pattern).display().to_string())
+            .map_err(|err| sup_error!(Error::SpecGlob(watch_path.to_path_buf(), err)))?
             .filter_map(|p| p.ok())
             .filter(|p| p.is_file())
-            .collect())
+            .collect();
+        files.sort_by(compare);
+        Ok(files)
     }
 
     pub fn initial_events(&mut self) -> Result<Vec<SpecWatcherEvent>> {
@@ -199,7 +344,9 @@ impl SpecWatcher {
             let desired_spec = desired_specs
                 .remove(name)
                 .expect("value should exist for key");
-            if active_spec != desired_spec {
+            // Running services only know their resolved group, so compare against the spec on
+            // disk with its group resolved the same way.
+            if active_spec != desired_spec.with_resolved_group() {
                 let remove_event = SpecWatcherEvent::RemoveService(active_spec);
                 let add_event = SpecWatcherEvent::AddService(desired_spec);
                 debug!(
@@ -276,6 +423,106 @@ impl SpecWatcher {
     }
 }
 
+/// A change to a spec file seen between two calls to `SpecPoller::poll`.
+#[derive(Debug, PartialEq)]
+pub enum SpecChange {
+    Added(ServiceSpec),
+    Modified(ServiceSpec),
+    Removed(ServiceSpec),
+}
+
+/// Tracks the spec files in a directory by modification time and size, reporting what changed
+/// since the previous poll. Unlike `SpecWatcher`, this doesn't rely on file system notifications
+/// and only re-reads spec files which look different on disk.
+pub struct SpecPoller {
+    watch_path: PathBuf,
+    known: HashMap<PathBuf, (SystemTime, u64, ServiceSpec)>,
+}
+
+impl SpecPoller {
+    pub fn new<P>(path: P) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        if !path.is_dir() {
+            return Err(sup_error!(Error::SpecWatcherDirNotFound(
+                path.display().to_string()
+            )));
+        }
+        Ok(SpecPoller {
+            watch_path: path,
+            known: HashMap::new(),
+        })
+    }
+
+    /// Returns the specs which were added, modified, or removed since the last poll. The first
+    /// poll reports every spec as added.
+    ///
+    /// Spec files which can't be parsed are skipped, and a previously loaded spec is kept as-is
+    /// until its file parses again.
+    pub fn poll(&mut self) -> Result<Vec<SpecChange>> {
+        let mut changes = Vec::new();
+        let mut seen = HashSet::new();
+        for spec_file in SpecWatcher::spec_files(&self.watch_path)? {
+            // A spec file may be removed after the directory was listed, in which case it's
+            // treated as if it had never been there.
+            let (mtime, len) = match fs::metadata(&spec_file)
+                .and_then(|m| m.modified().map(|mtime| (mtime, m.len())))
+            {
+                Ok(stat) => stat,
+                Err(_) => continue,
+            };
+            if let Some(&(known_mtime, known_len, _)) = self.known.get(&spec_file) {
+                if known_mtime == mtime && known_len == len {
+                    seen.insert(spec_file);
+                    continue;
+                }
+            }
+            let spec = match ServiceSpec::from_file(&spec_file) {
+                Ok(spec) => spec,
+                Err(e) => {
+                    match e.err {
+                        Error::ServiceSpecFileIO(_, _) => continue,
+                        Error::ServiceSpecParse(_) | Error::MissingRequiredIdent => {
+                            outputln!(
+                                "Error when loading service spec file '{}' ({}). \
+                                 This file will be skipped.",
+                                spec_file.display(),
+                                e.description()
+                            );
+                            if self.known.contains_key(&spec_file) {
+                                seen.insert(spec_file);
+                            }
+                            continue;
+                        }
+                        _ => return Err(e),
+                    }
+                }
+            };
+            seen.insert(spec_file.clone());
+            match self.known.insert(spec_file, (mtime, len, spec.clone())) {
+                Some((_, _, ref old)) if *old == spec => (),
+                Some(_) => changes.push(SpecChange::Modified(spec)),
+                None => changes.push(SpecChange::Added(spec)),
+            }
+        }
+
+        let mut removed: Vec<PathBuf> = self.known
+            .keys()
+            .filter(|p| !seen.contains(*p))
+            .cloned()
+            .collect();
+        removed.sort();
+        for path in removed {
+            if let Some((_, _, spec)) = self.known.remove(&path) {
+                changes.push(SpecChange::Removed(spec));
+            }
+        }
+        Ok(changes)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -291,7 +538,7 @@ mod test {
     use notify;
     use tempdir::TempDir;
 
-    use super::{SpecWatcher, SpecWatcherEvent};
+    use super::{SpecChange, SpecDirLock, SpecPoller, SpecWatcher, SpecWatcherEvent};
     use error::Error::*;
     use manager::service::ServiceSpec;
 
@@ -464,6 +711,30 @@ mod test {
         assert_eq!(events[1], SpecWatcherEvent::AddService(transformer_after));
     }
 
+    #[test]
+    fn new_events_unchanged_spec_with_unknown_key() {
+        let tmpdir = TempDir::new("fixture").unwrap();
+        let path = tmpdir.path().join("touched_spec");
+        fs::create_dir(&path).unwrap();
+        let mut file = fs::File::create(path.join("forward.spec")).unwrap();
+        file.write_all(b"ident = \"acme/forward\"\nfrom_the_future = true\n")
+            .unwrap();
+
+        let active_specs = map_for_specs(vec!["acme/forward"]);
+        let mut watcher = SpecWatcher::run_with::<TestWatcher, _>(&path).unwrap();
+        let start = Instant::now();
+        while !watcher.have_fs_events() {
+            assert!(
+                start.elapsed() < Duration::from_millis(1000),
+                "Waited for fs events but found none"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+        let events = watcher.new_events(active_specs).unwrap();
+
+        assert_eq!(events, vec![]);
+    }
+
     #[test]
     fn new_events_crazytown_with_active_specs() {
         let tmpdir = TempDir::new("fixture").unwrap();
@@ -547,6 +818,198 @@ mod test {
         assert!(events.contains(&SpecWatcherEvent::AddService(alpha)));
     }
 
+    #[test]
+    fn spec_files_are_sorted_by_file_stem() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        for name in vec!["delta", "alpha", "charlie", "echo", "bravo"] {
+            new_saved_spec(tmpdir.path(), &format!("acme/{}", name));
+        }
+
+        let names: Vec<String> = SpecWatcher::spec_files(tmpdir.path())
+            .unwrap()
+            .iter()
+            .map(|p| p.file_stem().unwrap().to_string_lossy().into_owned())
+            .collect();
+
+        assert_eq!(names, vec!["alpha", "bravo", "charlie", "delta", "echo"]);
+    }
+
+    #[test]
+    fn spec_files_sorted_by_custom_comparator() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        for name in vec!["bravo", "alpha", "charlie"] {
+            new_saved_spec(tmpdir.path(), &format!("acme/{}", name));
+        }
+
+        let names: Vec<String> =
+            SpecWatcher::spec_files_sorted_by(tmpdir.path(), |a, b| b.cmp(a))
+                .unwrap()
+                .iter()
+                .map(|p| p.file_stem().unwrap().to_string_lossy().into_owned())
+                .collect();
+
+        assert_eq!(names, vec!["charlie", "bravo", "alpha"]);
+    }
+
+    #[test]
+    fn spec_dir_lock_is_exclusive() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let lock = SpecDirLock::acquire(tmpdir.path()).unwrap();
+
+        match SpecDirLock::acquire(tmpdir.path()) {
+            Err(e) => match e.err {
+                SpecDirLocked(path, _) => assert_eq!(tmpdir.path(), path.as_path()),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec directory should already be locked"),
+        }
+
+        drop(lock);
+        assert!(SpecDirLock::acquire(tmpdir.path()).is_ok());
+    }
+
+    #[test]
+    fn spec_dir_lock_replaces_unreadable_lock() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let mut file = fs::File::create(tmpdir.path().join(".spec-dir.lock")).unwrap();
+        file.write_all(b"not a pid").unwrap();
+
+        let lock = SpecDirLock::acquire(tmpdir.path()).unwrap();
+        drop(lock);
+
+        assert!(!tmpdir.path().join(".spec-dir.lock").exists());
+    }
+
+    #[test]
+    fn spec_files_locked_holds_the_lock() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        new_saved_spec(tmpdir.path(), "acme/alpha");
+        let (lock, files) = SpecWatcher::spec_files_locked(tmpdir.path()).unwrap();
+
+        assert_eq!(files, vec![tmpdir.path().join("alpha.spec")]);
+        match SpecWatcher::spec_files_locked(tmpdir.path()) {
+            Err(e) => match e.err {
+                SpecDirLocked(path, _) => assert_eq!(tmpdir.path(), path.as_path()),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec directory should already be locked"),
+        }
+
+        drop(lock);
+        assert!(SpecWatcher::spec_files_locked(tmpdir.path()).is_ok());
+    }
+
+    #[test]
+    fn spec_files_invalid_glob_pattern() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let bad_path = tmpdir.path().join("bad[dir");
+
+        match SpecWatcher::spec_files(&bad_path) {
+            Err(e) => match e.err {
+                SpecGlob(path, _) => assert_eq!(bad_path, path),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Watch path should not be a valid glob pattern"),
+        }
+    }
+
+    #[test]
+    fn spec_files_with_custom_ext() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        new_saved_spec(tmpdir.path(), "acme/alpha");
+        for name in vec!["charlie", "bravo"] {
+            let spec = new_spec(&format!("acme/{}", name));
+            spec.to_file(tmpdir.path().join(spec.file_name_with_ext("spec.staged")))
+                .unwrap();
+        }
+
+        let staged = SpecWatcher::spec_files_with_ext(tmpdir.path(), "spec.staged").unwrap();
+
+        assert_eq!(
+            staged,
+            vec![
+                tmpdir.path().join("bravo.spec.staged"),
+                tmpdir.path().join("charlie.spec.staged"),
+            ]
+        );
+        assert_eq!(
+            SpecWatcher::spec_files(tmpdir.path()).unwrap(),
+            vec![tmpdir.path().join("alpha.spec")]
+        );
+    }
+
+    #[test]
+    fn spec_files_recursive_finds_nested_specs() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let nested = tmpdir.path().join("stack").join("web");
+        new_saved_spec(tmpdir.path(), "acme/alpha");
+        new_saved_spec(&nested, "acme/bravo");
+        new_saved_spec(&nested, "acme/charlie");
+
+        let top_level = SpecWatcher::spec_files(tmpdir.path()).unwrap();
+        let recursive = SpecWatcher::spec_files_recursive(tmpdir.path()).unwrap();
+
+        assert_eq!(top_level, vec![tmpdir.path().join("alpha.spec")]);
+        assert_eq!(
+            recursive,
+            vec![
+                tmpdir.path().join("alpha.spec"),
+                nested.join("bravo.spec"),
+                nested.join("charlie.spec"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spec_poller_dir_not_found() {
+        let tmpdir = TempDir::new("specs").unwrap();
+
+        match SpecPoller::new(tmpdir.path().join("nope")) {
+            Err(e) => match e.err {
+                SpecWatcherDirNotFound(_) => assert!(true),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Poller should fail to start without a directory"),
+        }
+    }
+
+    #[test]
+    fn spec_poller_add_modify_remove() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let mut poller = SpecPoller::new(tmpdir.path()).unwrap();
+        assert!(poller.poll().unwrap().is_empty());
+
+        let spec = new_saved_spec(tmpdir.path(), "acme/alpha");
+        assert_eq!(poller.poll().unwrap(), vec![SpecChange::Added(spec)]);
+        assert!(poller.poll().unwrap().is_empty());
+
+        let mut spec = new_spec("acme/alpha");
+        spec.group = String::from("production");
+        spec.to_file(tmpdir.path().join("alpha.spec")).unwrap();
+        assert_eq!(
+            poller.poll().unwrap(),
+            vec![SpecChange::Modified(spec.clone())]
+        );
+        assert!(poller.poll().unwrap().is_empty());
+
+        fs::remove_file(tmpdir.path().join("alpha.spec")).unwrap();
+        assert_eq!(poller.poll().unwrap(), vec![SpecChange::Removed(spec)]);
+        assert!(poller.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn spec_poller_ignores_spec_added_and_removed_between_polls() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let mut poller = SpecPoller::new(tmpdir.path()).unwrap();
+        let spec = new_saved_spec(tmpdir.path(), "acme/alpha");
+        assert_eq!(poller.poll().unwrap(), vec![SpecChange::Added(spec)]);
+
+        new_saved_spec(tmpdir.path(), "acme/bravo");
+        fs::remove_file(tmpdir.path().join("bravo.spec")).unwrap();
+
+        assert!(poller.poll().unwrap().is_empty());
+    }
+
     struct TestWatcher {
         tx: Sender<notify::DebouncedEvent>,
     }
@@ -578,6 +1041,15 @@ mod test {
                 .send(notify::DebouncedEvent::Write(toml_path))
                 .expect("couldn't send event");
         }
+
+        fn behavior_touched_spec<P: AsRef<Path>>(&mut self, path: P) {
+            let toml_path = path.as_ref().join("forward.spec");
+            let spec = ServiceSpec::from_file(&toml_path).expect("couldn't load spec file");
+            spec.to_file(&toml_path).expect("couldn't write spec file");
+            self.tx
+                .send(notify::DebouncedEvent::Write(toml_path))
+                .expect("couldn't send event");
+        }
     }
 
     impl notify::Watcher for TestWatcher {
@@ -605,6 +1077,7 @@ mod test {
                     self.behavior_removed_spec(path.as_ref());
                 }
                 "changed_spec" => self.behavior_changed_spec(path.as_ref()),
+                "touched_spec" => self.behavior_touched_spec(path.as_ref()),
                 "crazytown" => {
                     self.behavior_changed_spec(path.as_ref());
                     self.behavior_new_spec(path.as_ref());